@@ -0,0 +1,37 @@
+//! Compares the generic `Params` knot hash against the fixed-size core.
+//!
+//! Run with `cargo bench`.
+
+extern crate day10;
+
+use std::time::{Duration, Instant};
+
+const KEYS: usize = 20_000;
+
+fn time<T, F: FnMut(&[u8]) -> T>(keys: &[String], mut f: F) -> (Vec<T>, Duration) {
+    let start = Instant::now();
+    let results = keys.iter().map(|key| f(key.as_bytes())).collect();
+    (results, start.elapsed())
+}
+
+fn main() {
+    let keys: Vec<String> = (0..KEYS).map(|i| format!("flqrgnkx-{}", i)).collect();
+    let params = day10::Params::default();
+
+    let (expected, generic) = time(&keys, |key| params.hash(key).unwrap());
+    let (actual, fast) = time(&keys, day10::knot_hash);
+    for (e, a) in expected.iter().zip(actual.iter()) {
+        assert_eq!(&e[..], &a[..]);
+    }
+
+    let rate = |d: Duration| KEYS as f64 / d.as_secs_f64();
+    println!("{} keys", KEYS);
+    println!("generic: {:?} ({:.0} hashes/s)", generic, rate(generic));
+    println!("fast:    {:?} ({:.0} hashes/s)", fast, rate(fast));
+    println!("1M keys: {:.1}s", 1e6 / rate(fast));
+    println!("speedup: {:.1}x", generic.as_secs_f64() / fast.as_secs_f64());
+
+    let target = day10::fast::TARGET_HASHES_PER_SECOND;
+    println!("target:  {:.0} hashes/s", target);
+    assert!(rate(fast) >= target, "fast core missed its throughput target");
+}
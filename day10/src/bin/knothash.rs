@@ -0,0 +1,210 @@
+//! Hashes strings and files with the knot hash.
+//!
+//! Each argument is hashed as a string; with no arguments, each line of
+//! standard input is. With `--file`, the contents of each named file are
+//! hashed instead, and `-` or no names at all means standard input. Output
+//! follows `md5sum`: the digest, two spaces and the name of what was hashed.
+
+extern crate day10;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::process;
+
+const USAGE: &str = "\
+Usage: knothash [--hex | --binary | --grid] [--file] [NAME...]
+       knothash [--file] --check FILE
+  --hex          Print digests as 32 hex digits (default)
+  --binary       Print digests as 128 binary digits
+  --grid         Print digests as a row of 128 `#` (set) and `.` (clear)
+  --file         Hash the contents of the named files instead of the names;
+                 `-` or no names hashes all of standard input
+  --check FILE   Verify the `digest  name` lines in FILE (`-` for stdin)
+Without --file or names, each line of standard input is hashed as a string.";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Hex,
+    Binary,
+    Grid,
+}
+
+impl Format {
+    fn apply(self, digest: &[u8; 16]) -> String {
+        match self {
+            Format::Hex => day10::to_hex(digest),
+            Format::Binary => day10::to_binary(digest),
+            Format::Grid => day10::to_grid_row(digest),
+        }
+    }
+}
+
+/// Parses a digest written in any of the output formats.
+fn parse_digest(s: &str) -> Option<[u8; 16]> {
+    let (bits, digits) = match s.len() {
+        32 => (4, "0123456789abcdef"),
+        128 if s.chars().all(|c| c == '#' || c == '.') => (1, ".#"),
+        128 => (1, "01"),
+        _ => return None,
+    };
+
+    let mut digest = [0u8; 16];
+    for (i, c) in s.to_lowercase().chars().enumerate() {
+        let value = digits.find(c)? as u8;
+        digest[i * bits / 8] |= value << (8 - bits - i * bits % 8);
+    }
+    Some(digest)
+}
+
+/// Hashes the contents of a file, or of standard input if `path` is `-`.
+fn hash_file(path: &str) -> io::Result<[u8; 16]> {
+    let contents = if path == "-" {
+        let mut contents = Vec::new();
+        io::stdin().read_to_end(&mut contents)?;
+        contents
+    } else {
+        fs::read(path)?
+    };
+    Ok(day10::knot_hash(&contents[..]))
+}
+
+/// Verifies each `digest  name` line, printing `name: OK` or `name: FAILED`.
+/// Returns the number of names that did not match.
+fn check<R: BufRead, W: Write>(input: R, out: &mut W, files: bool) -> io::Result<usize> {
+    let mut failed = 0;
+    for line in input.lines() {
+        let line = line?;
+        if line.is_empty() {
+            continue;
+        }
+        let (expected, name) = match line.find("  ") {
+            Some(i) => (parse_digest(&line[..i]), &line[i + 2..]),
+            None => (None, ""),
+        };
+        let expected = match expected {
+            Some(expected) => expected,
+            None => {
+                writeln!(out, "{}: improperly formatted line", line)?;
+                failed += 1;
+                continue;
+            },
+        };
+
+        let actual = if files {
+            match hash_file(name) {
+                Ok(digest) => digest,
+                Err(e) => {
+                    writeln!(out, "{}: FAILED open or read ({})", name, e)?;
+                    failed += 1;
+                    continue;
+                },
+            }
+        } else {
+            day10::knot_hash(name.as_bytes())
+        };
+
+        if actual == expected {
+            writeln!(out, "{}: OK", name)?;
+        } else {
+            writeln!(out, "{}: FAILED", name)?;
+            failed += 1;
+        }
+    }
+    Ok(failed)
+}
+
+fn main() {
+    let mut format = Format::Hex;
+    let mut files = false;
+    let mut check_file = None;
+    let mut names = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--hex" => format = Format::Hex,
+            "--binary" => format = Format::Binary,
+            "--grid" => format = Format::Grid,
+            "--file" => files = true,
+            "--check" => check_file = Some(args.next().expect(USAGE)),
+            "--help" => {
+                println!("{}", USAGE);
+                return;
+            },
+            _ => names.push(arg),
+        }
+    }
+
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    if let Some(path) = check_file {
+        let failed = if path == "-" {
+            check(stdin.lock(), &mut out, files)
+        } else {
+            let file = File::open(&path)
+                .unwrap_or_else(|e| panic!("Unable to read {}: {}", path, e));
+            check(BufReader::new(file), &mut out, files)
+        }.expect("I/O error");
+        if failed > 0 {
+            eprintln!("knothash: WARNING: {} computed checksums did NOT match", failed);
+            process::exit(1);
+        }
+        return;
+    }
+
+    if names.is_empty() && files {
+        names.push("-".to_string());
+    }
+
+    if names.is_empty() {
+        for line in stdin.lock().lines() {
+            let line = line.expect("Unable to read standard input");
+            let digest = day10::knot_hash(line.as_bytes());
+            writeln!(out, "{}  {}", format.apply(&digest), line).expect("I/O error");
+        }
+        return;
+    }
+
+    for name in names.iter() {
+        let digest = if files {
+            hash_file(name).unwrap_or_else(|e| panic!("Unable to read {}: {}", name, e))
+        } else {
+            day10::knot_hash(name.as_bytes())
+        };
+        writeln!(out, "{}  {}", format.apply(&digest), name).expect("I/O error");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_digest() {
+        let digest = day10::knot_hash(b"AoC 2017");
+        for &format in &[Format::Hex, Format::Binary, Format::Grid] {
+            assert_eq!(parse_digest(&format.apply(&digest)), Some(digest));
+        }
+        assert_eq!(parse_digest("33EFEB34EA91902BB2F59C9920CAA6CD"), Some(digest));
+        assert_eq!(parse_digest("33efeb34"), None);
+        assert_eq!(parse_digest("z3efeb34ea91902bb2f59c9920caa6cd"), None);
+    }
+
+    #[test]
+    fn test_check() {
+        let input = "33efeb34ea91902bb2f59c9920caa6cd  AoC 2017\n\
+                     3efbe78a8d82f29979031a4aa0b16a9d  1,2,4\n\
+                     not a digest\n";
+        let mut out = Vec::new();
+        assert_eq!(check(input.as_bytes(), &mut out, false).unwrap(), 2);
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.lines().collect::<Vec<_>>(), vec![
+            "AoC 2017: OK",
+            "1,2,4: FAILED",
+            "not a digest: improperly formatted line",
+        ]);
+    }
+}
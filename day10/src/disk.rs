@@ -0,0 +1,125 @@
+//! The disk grid from day 14, built from knot hashes.
+//!
+//! Row `n` of the grid for a key is the knot hash of `key-n`, with each set
+//! bit marking a used square.
+
+use std::fmt;
+
+use ::knot_hash;
+
+pub const SIZE: usize = 128;
+
+pub struct Disk {
+    rows: Vec<[u8; 16]>,
+}
+
+impl Disk {
+    pub fn new(key: &str) -> Disk {
+        Disk {
+            rows: (0..SIZE)
+                .map(|n| knot_hash(format!("{}-{}", key, n).as_bytes()))
+                .collect(),
+        }
+    }
+
+    pub fn is_used(&self, row: usize, col: usize) -> bool {
+        self.rows[row][col / 8] & (0x80 >> (col % 8)) != 0
+    }
+
+    pub fn used_count(&self) -> usize {
+        self.rows.iter()
+            .flat_map(|row| row.iter())
+            .map(|b| b.count_ones() as usize)
+            .sum()
+    }
+
+    /// Labels each used square with the number of the region it belongs to.
+    /// Regions are numbered from 1 in reading order; free squares are 0.
+    pub fn regions(&self) -> Regions {
+        let mut labels = vec![0; SIZE * SIZE];
+        let mut count = 0;
+        let mut stack = Vec::new();
+        for start in 0..SIZE * SIZE {
+            if labels[start] != 0 || !self.is_used(start / SIZE, start % SIZE) {
+                continue;
+            }
+
+            count += 1;
+            labels[start] = count;
+            stack.push(start);
+            while let Some(square) = stack.pop() {
+                let (row, col) = (square / SIZE, square % SIZE);
+                let neighbours = [
+                    (row.wrapping_sub(1), col),
+                    (row + 1, col),
+                    (row, col.wrapping_sub(1)),
+                    (row, col + 1),
+                ];
+                for &(r, c) in neighbours.iter() {
+                    if r < SIZE && c < SIZE && labels[r * SIZE + c] == 0 && self.is_used(r, c) {
+                        labels[r * SIZE + c] = count;
+                        stack.push(r * SIZE + c);
+                    }
+                }
+            }
+        }
+        Regions { labels, count }
+    }
+}
+
+/// Renders the grid as 128 lines of `#` (used) and `.` (free).
+impl fmt::Display for Disk {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in self.rows.iter() {
+            writeln!(f, "{}", ::to_grid_row(row))?;
+        }
+        Ok(())
+    }
+}
+
+pub struct Regions {
+    labels: Vec<usize>,
+    count: usize,
+}
+
+impl Regions {
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// The region containing a square, or 0 if it's free.
+    pub fn label(&self, row: usize, col: usize) -> usize {
+        self.labels[row * SIZE + col]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_example() {
+        let disk = Disk::new("flqrgnkx");
+        let rendered = disk.to_string();
+        let corner: Vec<&str> = rendered.lines().take(8).map(|line| &line[..8]).collect();
+        assert_eq!(corner, vec![
+            "##.#.#..",
+            ".#.#.#.#",
+            "....#.#.",
+            "#.#.##.#",
+            ".##.#...",
+            "##..#..#",
+            ".#...#..",
+            "##.#.##.",
+        ]);
+        assert_eq!(disk.used_count(), 8108);
+
+        let regions = disk.regions();
+        assert_eq!(regions.count(), 1242);
+        assert_eq!(regions.label(0, 0), 1);
+        assert_eq!(regions.label(0, 1), 1);
+        assert_eq!(regions.label(1, 1), 1);
+        assert_eq!(regions.label(0, 2), 0);
+        assert_eq!(regions.label(0, 3), 2);
+    }
+}
@@ -0,0 +1,380 @@
+pub mod disk;
+pub mod fast;
+#[cfg(test)]
+mod vectors;
+
+use std::fmt;
+use std::hash::Hasher;
+use std::iter::FromIterator;
+
+pub struct Circle {
+    pub values: Vec<usize>,
+}
+
+impl Circle {
+    pub fn new(length: usize) -> Circle {
+        Circle {
+            values: Vec::from_iter(0..length),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    fn reverse_part(&mut self, index: usize, length: usize) {
+        // Normally we could use slice's own reverse() method, but it doesn't
+        // exactly deal well with the circular nature of this wrapping thing.
+        if length <= 1 {
+            // Nothing to do
+            return;
+        }
+
+        let len = self.values.len();
+        let mut a = index;
+        let mut b = index + length - 1;
+        while a < b {
+            self.values.swap(a % len, b % len);
+            a += 1;
+            b -= 1;
+        }
+    }
+
+}
+
+pub struct KnotHasher {
+    position: usize,
+    skip_size: usize,
+}
+
+impl KnotHasher {
+    pub fn new() -> KnotHasher {
+        KnotHasher {
+            position: 0,
+            skip_size: 0,
+        }
+    }
+
+    pub fn tie_knots(&mut self, circle: &mut Circle, lengths: &[usize]) {
+        for &length in lengths.iter() {
+            circle.reverse_part(self.position, length);
+            self.position = (self.position + length + self.skip_size) % circle.len();
+            self.skip_size += 1;
+        }
+    }
+}
+
+impl Default for KnotHasher {
+    fn default() -> KnotHasher {
+        KnotHasher::new()
+    }
+}
+
+/// The parameters of a knot hash. The defaults are the ones from the puzzle;
+/// use `Params::builder()` to make a variant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Params {
+    ring_size: usize,
+    rounds: usize,
+    suffix: Vec<u8>,
+    block_size: usize,
+}
+
+impl Params {
+    pub fn builder() -> ParamsBuilder {
+        ParamsBuilder {
+            params: Params::default(),
+        }
+    }
+
+    pub fn ring_size(&self) -> usize {
+        self.ring_size
+    }
+
+    pub fn rounds(&self) -> usize {
+        self.rounds
+    }
+
+    pub fn suffix(&self) -> &[u8] {
+        &self.suffix[..]
+    }
+
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// The number of bytes in a digest made with these parameters.
+    pub fn digest_len(&self) -> usize {
+        self.ring_size / self.block_size
+    }
+
+    /// Computes the knot hash of `input` with these parameters. Fails if
+    /// any byte of the input is longer than the ring.
+    pub fn hash(&self, input: &[u8]) -> Result<Vec<u8>, ParamError> {
+        if let Some(&length) = input.iter().find(|&&b| b as usize > self.ring_size) {
+            return Err(ParamError::LengthTooLong(length as usize));
+        }
+
+        let lengths: Vec<usize> = input.iter()
+            .chain(self.suffix.iter())
+            .map(|&n| n as usize)
+            .collect();
+
+        let mut circle = Circle::new(self.ring_size);
+        let mut hasher = KnotHasher::new();
+        for _ in 0..self.rounds {
+            hasher.tie_knots(&mut circle, &lengths[..]);
+        }
+
+        Ok(circle.values[..].chunks(self.block_size)
+            .map(|chunk| chunk.iter().fold(0, |a, &b| a ^ b) as u8)
+            .collect())
+    }
+}
+
+impl Default for Params {
+    fn default() -> Params {
+        Params {
+            ring_size: 256,
+            rounds: 64,
+            suffix: vec![17, 31, 73, 47, 23],
+            block_size: 16,
+        }
+    }
+}
+
+pub struct ParamsBuilder {
+    params: Params,
+}
+
+impl ParamsBuilder {
+    pub fn ring_size(mut self, ring_size: usize) -> ParamsBuilder {
+        self.params.ring_size = ring_size;
+        self
+    }
+
+    pub fn rounds(mut self, rounds: usize) -> ParamsBuilder {
+        self.params.rounds = rounds;
+        self
+    }
+
+    pub fn suffix(mut self, suffix: &[u8]) -> ParamsBuilder {
+        self.params.suffix = suffix.to_vec();
+        self
+    }
+
+    pub fn block_size(mut self, block_size: usize) -> ParamsBuilder {
+        self.params.block_size = block_size;
+        self
+    }
+
+    pub fn build(self) -> Result<Params, ParamError> {
+        let params = self.params;
+        // Ring values are folded into bytes, so they must all fit in a u8.
+        if params.ring_size == 0 || params.ring_size > 256 {
+            return Err(ParamError::RingSize(params.ring_size));
+        }
+        if params.block_size == 0 || !params.ring_size.is_multiple_of(params.block_size) {
+            return Err(ParamError::BlockSize(params.ring_size, params.block_size));
+        }
+        if let Some(&length) = params.suffix.iter().find(|&&b| b as usize > params.ring_size) {
+            return Err(ParamError::LengthTooLong(length as usize));
+        }
+        Ok(params)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamError {
+    /// The ring must have between 1 and 256 elements.
+    RingSize(usize),
+    /// The block size must evenly divide the ring size.
+    BlockSize(usize, usize),
+    /// A length (from the input or suffix) is longer than the ring.
+    LengthTooLong(usize),
+}
+
+impl fmt::Display for ParamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParamError::RingSize(size) =>
+                write!(f, "ring size {} is not between 1 and 256", size),
+            ParamError::BlockSize(ring, block) =>
+                write!(f, "block size {} does not divide ring size {}", block, ring),
+            ParamError::LengthTooLong(length) =>
+                write!(f, "length {} is longer than the ring", length),
+        }
+    }
+}
+
+/// Computes the knot hash of `input`.
+pub fn knot_hash(input: &[u8]) -> [u8; 16] {
+    fast::hash(input)
+}
+
+/// Formats a digest as lowercase hexadecimal, two digits per byte.
+pub fn to_hex(digest: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut hex = String::with_capacity(digest.len() * 2);
+    for &b in digest.iter() {
+        hex.push(DIGITS[(b >> 4) as usize] as char);
+        hex.push(DIGITS[(b & 0xf) as usize] as char);
+    }
+    hex
+}
+
+/// Formats a digest as binary, eight digits per byte, most significant bit
+/// first.
+pub fn to_binary(digest: &[u8]) -> String {
+    digest.iter().map(|b| format!("{:08b}", b)).collect()
+}
+
+/// Formats a digest as a row of squares: `#` for each set bit and `.` for
+/// each clear one.
+pub fn to_grid_row(digest: &[u8]) -> String {
+    to_binary(digest).chars()
+        .map(|c| if c == '1' { '#' } else { '.' })
+        .collect()
+}
+
+/// A knot hash that can be fed its input in pieces.
+///
+/// Every round of the hash replays the entire input, so the input is
+/// buffered until `finalize` is called. `finalize` doesn't consume the
+/// hasher, so more input can be added afterwards.
+///
+/// `KnotHash` also implements `Hasher`, so it can be used for a `HashMap`
+/// through `BuildHasherDefault<KnotHash>`.
+#[derive(Debug, Clone, Default)]
+pub struct KnotHash {
+    input: Vec<u8>,
+}
+
+impl KnotHash {
+    pub fn new() -> KnotHash {
+        KnotHash::default()
+    }
+
+    pub fn update(&mut self, input: &[u8]) -> &mut KnotHash {
+        self.input.extend_from_slice(input);
+        self
+    }
+
+    pub fn finalize(&self) -> [u8; 16] {
+        fast::hash(&self.input[..])
+    }
+}
+
+impl Hasher for KnotHash {
+    fn write(&mut self, bytes: &[u8]) {
+        self.update(bytes);
+    }
+
+    /// Returns the first 8 bytes of the digest.
+    fn finish(&self) -> u64 {
+        let digest = self.finalize();
+        digest[..8].iter().fold(0, |a, &b| a << 8 | b as u64)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+    use std::hash::BuildHasherDefault;
+
+    #[test]
+    fn test_tie_knots() {
+        let lengths = [3, 4, 1, 5];
+        let mut circle = Circle::new(5);
+        let mut hasher = KnotHasher::new();
+        hasher.tie_knots(&mut circle, &lengths);
+        assert_eq!(circle.values[0], 3);
+        assert_eq!(circle.values[1], 4);
+    }
+
+    #[test]
+    fn test_known_vectors() {
+        assert_eq!(to_hex(&knot_hash(b"")), "a2582a3a0e66e6e86e3812dcb672a272");
+        assert_eq!(to_hex(&knot_hash(b"AoC 2017")), "33efeb34ea91902bb2f59c9920caa6cd");
+        assert_eq!(to_hex(&knot_hash(b"1,2,3")), "3efbe78a8d82f29979031a4aa0b16a9d");
+        assert_eq!(to_hex(&knot_hash(b"1,2,4")), "63960835bcdc130f0b66d7ff4f6a5a8e");
+    }
+
+    #[test]
+    fn test_streaming() {
+        let digest = knot_hash(b"1,2,3");
+        assert_eq!(to_hex(&digest), "3efbe78a8d82f29979031a4aa0b16a9d");
+        assert_eq!(&to_binary(&digest)[..8], "00111110");
+        assert_eq!(&to_grid_row(&digest)[..8], "..#####.");
+
+        let mut hash = KnotHash::new();
+        hash.update(b"1,").update(b"2");
+        hash.update(b",3");
+        assert_eq!(hash.finalize(), digest);
+        assert_eq!(hash.finish(), 0x3efbe78a8d82f299);
+    }
+
+    #[test]
+    fn test_default_params() {
+        let params = Params::builder().build().unwrap();
+        assert_eq!(params, Params::default());
+        assert_eq!(params.digest_len(), 16);
+        for &(input, expected) in &[
+            ("", "a2582a3a0e66e6e86e3812dcb672a272"),
+            ("AoC 2017", "33efeb34ea91902bb2f59c9920caa6cd"),
+            ("1,2,4", "63960835bcdc130f0b66d7ff4f6a5a8e"),
+        ] {
+            let digest = params.hash(input.as_bytes()).unwrap();
+            assert_eq!(to_hex(&digest), expected);
+        }
+    }
+
+    #[test]
+    fn test_variant_params() {
+        // A single round with no suffix and one block is just part 1,
+        // folded down to one byte.
+        let params = Params::builder()
+            .ring_size(5)
+            .rounds(1)
+            .suffix(&[])
+            .block_size(5)
+            .build()
+            .unwrap();
+        assert_eq!(params.hash(&[3, 4, 1, 5]), Ok(vec![3 ^ 4 ^ 2 ^ 1]));
+        assert_eq!(params.hash(&[6]), Err(ParamError::LengthTooLong(6)));
+
+        let params = Params::builder().ring_size(128).block_size(8).build().unwrap();
+        assert_eq!(params.hash(b"flqrgnkx").unwrap().len(), 16);
+
+        let params = Params::builder().ring_size(128).block_size(32).build().unwrap();
+        let digest = params.hash(b"flqrgnkx").unwrap();
+        assert_eq!(digest.len(), 4);
+        assert_eq!(to_hex(&digest).len(), 8);
+        assert_eq!(to_grid_row(&digest).len(), 32);
+    }
+
+    #[test]
+    fn test_invalid_params() {
+        assert_eq!(Params::builder().ring_size(0).build(), Err(ParamError::RingSize(0)));
+        assert_eq!(Params::builder().ring_size(300).build(), Err(ParamError::RingSize(300)));
+        assert_eq!(Params::builder().block_size(0).build(), Err(ParamError::BlockSize(256, 0)));
+        assert_eq!(Params::builder().ring_size(100).block_size(16).build(),
+            Err(ParamError::BlockSize(100, 16)));
+        assert_eq!(Params::builder().ring_size(16).build(),
+            Err(ParamError::LengthTooLong(17)));
+    }
+
+    #[test]
+    fn test_hash_map() {
+        let mut map: HashMap<&str, usize, BuildHasherDefault<KnotHash>> = HashMap::default();
+        map.insert("flqrgnkx", 1);
+        map.insert("AoC 2017", 2);
+        assert_eq!(map["flqrgnkx"], 1);
+        assert_eq!(map["AoC 2017"], 2);
+    }
+}
@@ -0,0 +1,90 @@
+//! The checked-in knot hash corpus in `vectors.txt`, and the generator that
+//! produced it.
+//!
+//! The corpus is generated with `reference_hash`, a direct transcription of
+//! the puzzle description that shares no code with the rest of the crate.
+//! To regenerate it after changing the inputs, run
+//! `cargo test --lib -- --ignored generate_corpus`.
+
+use std::fs;
+
+use {knot_hash, to_hex};
+
+/// The knot hash exactly as the puzzle describes it, without any of the
+/// crate's own machinery.
+fn reference_hash(input: &[u8]) -> String {
+    let mut lengths: Vec<usize> = input.iter().map(|&b| b as usize).collect();
+    lengths.extend_from_slice(&[17, 31, 73, 47, 23]);
+
+    let mut list: Vec<usize> = (0..256).collect();
+    let mut position = 0;
+    let mut skip_size = 0;
+    for _ in 0..64 {
+        for &length in lengths.iter() {
+            let sublist: Vec<usize> = (0..length).map(|i| list[(position + i) % 256]).collect();
+            for (i, value) in sublist.into_iter().rev().enumerate() {
+                list[(position + i) % 256] = value;
+            }
+            position += length + skip_size;
+            skip_size += 1;
+        }
+    }
+
+    list.chunks(16)
+        .map(|block| format!("{:02x}", block.iter().fold(0, |a, b| a ^ b)))
+        .collect()
+}
+
+/// The inputs of the corpus: the day 14 example keys, then printable ASCII
+/// strings of increasing length from a fixed xorshift sequence.
+fn corpus_inputs() -> Vec<String> {
+    let mut inputs: Vec<String> = (0..8).map(|n| format!("flqrgnkx-{}", n)).collect();
+    let mut seed = 0x2017_0a10_u64;
+    let mut next = || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+    for max_len in 1..81 {
+        let len = 1 + (next() % max_len) as usize;
+        // No leading or trailing spaces, which are easy to lose in editing
+        let input: String = (0..len).map(|_| (b' ' + (next() % 95) as u8) as char).collect();
+        let input = input.trim();
+        inputs.push(if input.is_empty() { "x".to_string() } else { input.to_string() });
+    }
+    inputs
+}
+
+#[test]
+fn test_corpus() {
+    let corpus = include_str!("vectors.txt");
+    let mut count = 0;
+    for line in corpus.lines().filter(|line| !line.starts_with('#')) {
+        let (digest, input) = line.split_at(32);
+        assert!(input.starts_with("  "));
+        let input = &input[2..];
+        assert_eq!(to_hex(&knot_hash(input.as_bytes())), digest, "input {:?}", input);
+        count += 1;
+    }
+    assert!(count > 0);
+}
+
+#[test]
+fn test_reference() {
+    assert_eq!(reference_hash(b"AoC 2017"), "33efeb34ea91902bb2f59c9920caa6cd");
+}
+
+#[test]
+#[ignore]
+fn generate_corpus() {
+    let mut corpus = String::from("\
+# Knot hash corpus: `digest  input`, one pair per line.
+# Generated by `generate_corpus` in vectors.rs; see there before editing.
+");
+    for input in corpus_inputs() {
+        corpus.push_str(&format!("{}  {}\n", reference_hash(input.as_bytes()), input));
+    }
+    fs::write(concat!(env!("CARGO_MANIFEST_DIR"), "/src/vectors.txt"), corpus)
+        .expect("Unable to write corpus");
+}
@@ -0,0 +1,32 @@
+//! Compares the fast Part 2 engine against the interpreter.
+//!
+//! Run with `cargo bench`.
+
+extern crate day5;
+
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 20;
+
+fn time<T, F: FnMut() -> T>(mut f: F) -> (T, Duration) {
+    let start = Instant::now();
+    let mut result = f();
+    for _ in 1..ITERATIONS {
+        result = f();
+    }
+    (result, start.elapsed())
+}
+
+fn main() {
+    let input = include_str!("../src/input.txt");
+    let instructions = day5::parse_instructions(input);
+
+    let (expected, interpreted) = time(|| day5::escape_maze_2(instructions.clone()));
+    let (actual, fast) = time(|| day5::fast::escape_maze_2::<i32>(&instructions));
+    assert_eq!(Ok(expected), actual);
+
+    println!("{} runs of {} steps", ITERATIONS, expected);
+    println!("interpreter: {:?}", interpreted);
+    println!("fast:        {:?}", fast);
+    println!("speedup:     {:.1}x", interpreted.as_secs_f64() / fast.as_secs_f64());
+}
@@ -0,0 +1,171 @@
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, Write};
+
+use super::Maze;
+
+const HELP: &str = "\
+Commands:
+  s, step [N]     Perform N jumps (default 1)
+  r, run N        Run until step N has been performed
+  c, continue     Run until a breakpoint is hit or the maze is escaped
+  b, break I      Toggle a breakpoint on index I
+  d, dump         Show the offsets, marking the current index
+  q, quit         Exit the debugger";
+
+/// Why a run of the maze stopped.
+enum Stop {
+    Escaped,
+    Breakpoint,
+    Done,
+}
+
+struct Debugger<F> {
+    maze: Maze<F>,
+    breakpoints: BTreeSet<usize>,
+}
+
+impl<F> Debugger<F> where F: Fn(isize) -> isize {
+    /// Jumps until `until` returns true, a breakpoint is reached or the maze
+    /// is escaped. `until` is checked before the first jump, so a target that
+    /// has already been reached doesn't move the maze. Breakpoints are only
+    /// checked after a jump, so continuing from one makes progress.
+    fn run_until<P, W>(&mut self, out: &mut W, verbose: bool, until: P) -> io::Result<Stop>
+        where P: Fn(&Maze<F>) -> bool, W: Write
+    {
+        if self.maze.is_escaped() {
+            return Ok(Stop::Escaped);
+        }
+        if until(&self.maze) {
+            return Ok(Stop::Done);
+        }
+        loop {
+            let jump = match self.maze.step() {
+                Some(jump) => jump,
+                None => return Ok(Stop::Escaped),
+            };
+            if verbose {
+                writeln!(out, "{}", jump)?;
+            }
+            if self.maze.is_escaped() {
+                return Ok(Stop::Escaped);
+            }
+            if until(&self.maze) {
+                return Ok(Stop::Done);
+            }
+            if self.breakpoints.contains(&(self.maze.index as usize)) {
+                return Ok(Stop::Breakpoint);
+            }
+        }
+    }
+
+    fn report<W: Write>(&self, out: &mut W, stop: Stop) -> io::Result<()> {
+        match stop {
+            Stop::Escaped =>
+                writeln!(out, "Escaped after {} steps", self.maze.steps),
+            Stop::Breakpoint =>
+                writeln!(out, "Breakpoint at index {} (step {})",
+                    self.maze.index, self.maze.steps),
+            Stop::Done =>
+                writeln!(out, "At index {} (step {})", self.maze.index, self.maze.steps),
+        }
+    }
+
+    fn dump<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        for (i, offset) in self.maze.offsets.iter().enumerate() {
+            let marker = if i as isize == self.maze.index { '>' } else { ' ' };
+            let bp = if self.breakpoints.contains(&i) { '*' } else { ' ' };
+            writeln!(out, "{}{}{:5}: {}", marker, bp, i, offset)?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs an interactive debugging session on the maze, reading commands from
+/// `input` until it is exhausted or the user quits.
+pub fn run<F, R, W>(maze: Maze<F>, input: R, mut out: W) -> io::Result<()>
+    where F: Fn(isize) -> isize, R: BufRead, W: Write
+{
+    let mut debugger = Debugger {
+        maze,
+        breakpoints: BTreeSet::new(),
+    };
+
+    writeln!(out, "{}", HELP)?;
+    for line in input.lines() {
+        let line = line?;
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some(command) => command,
+            None => continue,
+        };
+        let arg = words.next().map(|s| s.parse::<usize>());
+
+        match (command, arg) {
+            ("s", None) | ("step", None) => {
+                let target = debugger.maze.steps + 1;
+                let stop = debugger.run_until(&mut out, true, |m| m.steps >= target)?;
+                debugger.report(&mut out, stop)?;
+            },
+            ("s", Some(Ok(n))) | ("step", Some(Ok(n))) => {
+                let target = debugger.maze.steps + n;
+                let stop = debugger.run_until(&mut out, true, |m| m.steps >= target)?;
+                debugger.report(&mut out, stop)?;
+            },
+            ("r", Some(Ok(n))) | ("run", Some(Ok(n))) => {
+                let stop = debugger.run_until(&mut out, false, |m| m.steps >= n)?;
+                debugger.report(&mut out, stop)?;
+            },
+            ("c", None) | ("continue", None) => {
+                let stop = debugger.run_until(&mut out, false, |_| false)?;
+                debugger.report(&mut out, stop)?;
+            },
+            ("b", Some(Ok(i))) | ("break", Some(Ok(i))) => {
+                if debugger.breakpoints.insert(i) {
+                    writeln!(out, "Breakpoint set on index {}", i)?;
+                } else {
+                    debugger.breakpoints.remove(&i);
+                    writeln!(out, "Breakpoint cleared on index {}", i)?;
+                }
+            },
+            ("d", None) | ("dump", None) => debugger.dump(&mut out)?,
+            ("q", None) | ("quit", None) => break,
+            _ => writeln!(out, "Invalid command: {}\n{}", line, HELP)?,
+        }
+        out.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_session() {
+        let maze = Maze::new(::parse_instructions("0\n3\n0\n1\n-3"), ::advance_1);
+        let input = "s\n\
+                     r 3\n\
+                     r 1\n\
+                     s 0\n\
+                     b 1\n\
+                     c\n\
+                     c\n\
+                     s\n\
+                     q\n\
+                     s\n";
+        let mut out = Vec::new();
+        run(maze, input.as_bytes(), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.lines().skip(HELP.lines().count()).collect::<Vec<_>>(), vec![
+            "step 1: [0] 0 -> 1",
+            "At index 0 (step 1)",
+            "At index 4 (step 3)",
+            "At index 4 (step 3)",
+            "At index 4 (step 3)",
+            "Breakpoint set on index 1",
+            "Breakpoint at index 1 (step 4)",
+            "Escaped after 5 steps",
+            "Escaped after 5 steps",
+        ]);
+    }
+}
@@ -0,0 +1,158 @@
+pub mod debugger;
+pub mod fast;
+pub mod rule;
+pub mod trace;
+
+use trace::Jump;
+
+pub fn parse_instructions(input: &str) -> Vec<isize> {
+    let mut instructions = Vec::new();
+    for line in input.lines() {
+        let offset = match line.parse::<isize>() {
+            Ok(n) => n,
+            Err(_) => panic!("Unable to parse input line: {}", line),
+        };
+        instructions.push(offset);
+    }
+    instructions
+}
+
+pub fn advance_1(n: isize) -> isize {
+    n + 1
+}
+
+pub fn advance_2(n: isize) -> isize {
+    if n >= 3 { n - 1 } else { n + 1 }
+}
+
+pub fn escape_maze_1(instructions: Vec<isize>) -> usize {
+    escape_maze_impl(instructions, advance_1)
+}
+
+pub fn escape_maze_2(instructions: Vec<isize>) -> usize {
+    escape_maze_impl(instructions, advance_2)
+}
+
+pub fn escape_maze_impl<F>(instructions: Vec<isize>, advance: F) -> usize
+    where F: Fn(isize) -> isize
+{
+    let mut maze = Maze::new(instructions, advance);
+    while maze.step().is_some() {}
+    maze.steps
+}
+
+/// Like `escape_maze_impl`, but gives up and returns `None` if the maze
+/// hasn't been escaped after `max_steps` jumps.
+pub fn escape_maze_limited<F>(instructions: Vec<isize>, advance: F, max_steps: usize) -> Option<usize>
+    where F: Fn(isize) -> isize
+{
+    let mut maze = Maze::new(instructions, advance);
+    while maze.steps < max_steps {
+        if maze.step().is_none() {
+            return Some(maze.steps);
+        }
+    }
+    if maze.is_escaped() { Some(maze.steps) } else { None }
+}
+
+pub fn escape_maze_traced<F, T>(instructions: Vec<isize>, advance: F, tracer: &mut T) -> usize
+    where F: Fn(isize) -> isize, T: trace::Tracer
+{
+    let mut maze = Maze::new(instructions, advance);
+    while let Some(jump) = maze.step() {
+        tracer.jump(&jump);
+    }
+    maze.steps
+}
+
+/// The state of a maze that is being escaped one jump at a time.
+pub struct Maze<F> {
+    offsets: Vec<isize>,
+    index: isize,
+    steps: usize,
+    advance: F,
+}
+
+impl<F> Maze<F> where F: Fn(isize) -> isize {
+    pub fn new(offsets: Vec<isize>, advance: F) -> Maze<F> {
+        Maze {
+            offsets,
+            index: 0,
+            steps: 0,
+            advance,
+        }
+    }
+
+    pub fn is_escaped(&self) -> bool {
+        self.index < 0 || self.index as usize >= self.offsets.len()
+    }
+
+    /// Performs a single jump, or returns `None` if the maze has already been
+    /// escaped.
+    pub fn step(&mut self) -> Option<Jump> {
+        if self.is_escaped() {
+            return None;
+        }
+
+        let index = self.index as usize;
+        let offset = &mut self.offsets[index];
+        let before = *offset;
+        // A jump too far to represent is certainly out of the maze
+        self.index = self.index.checked_add(before)
+            .unwrap_or(if before < 0 { -1 } else { isize::MAX });
+        *offset = (self.advance)(before);
+        self.steps += 1;
+
+        Some(Jump {
+            step: self.steps,
+            index,
+            before,
+            after: *offset,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use trace::TraceFilter;
+
+    #[test]
+    fn test_1() {
+        let input = "0\n3\n0\n1\n-3";
+        let instructions = parse_instructions(input);
+        assert_eq!(escape_maze_1(instructions), 5);
+    }
+
+    #[test]
+    fn test_2() {
+        let input = "0\n3\n0\n1\n-3";
+        let instructions = parse_instructions(input);
+        assert_eq!(escape_maze_2(instructions), 10);
+    }
+
+    #[test]
+    fn test_limited() {
+        let instructions = parse_instructions("0\n3\n0\n1\n-3");
+        assert_eq!(escape_maze_limited(instructions.clone(), advance_1, 5), Some(5));
+        assert_eq!(escape_maze_limited(instructions, advance_1, 4), None);
+        assert_eq!(escape_maze_limited(vec![0, 1], |_| 0, 1000), None);
+    }
+
+    #[test]
+    fn test_index_overflow() {
+        assert_eq!(escape_maze_impl(vec![1, isize::MAX], |n| n), 2);
+        assert_eq!(escape_maze_impl(vec![2, 0, isize::MIN], |n| n), 2);
+    }
+
+    #[test]
+    fn test_trace() {
+        let input = "0\n3\n0\n1\n-3";
+        let instructions = parse_instructions(input);
+        let mut log = trace::TraceLog::new(TraceFilter::all());
+        assert_eq!(escape_maze_traced(instructions, advance_1, &mut log), 5);
+        assert_eq!(log.jumps.len(), 5);
+        assert_eq!(log.jumps[0], Jump { step: 1, index: 0, before: 0, after: 1 });
+        assert_eq!(log.jumps[4], Jump { step: 5, index: 1, before: 4, after: 5 });
+    }
+}
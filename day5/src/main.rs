@@ -1,70 +1,114 @@
+extern crate day5;
+
+use std::env;
+use std::io;
+
+use day5::{advance_1, advance_2, debugger, fast, trace, Maze};
+use day5::rule::Rule;
+use day5::trace::{TraceFilter, TraceWriter};
+
 fn main() {
     let input = include_str!("input.txt");
 
-    let instructions = parse_instructions(input);
+    let instructions = day5::parse_instructions(input);
 
-    let steps_1 = escape_maze_1(instructions.clone());
-    println!("Part 1 = {}", steps_1);
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(|s| s.as_str()) {
+        Some("--trace") => run_trace(instructions, &args[1..]),
+        Some("--debug") => run_debug(instructions, &args[1..]),
+        Some("--fast") => match fast::escape_maze_2::<i32>(&instructions) {
+            Ok(steps_2) => println!("Part 2 = {}", steps_2),
+            Err(e) => panic!("Unable to use fast engine: {}", e),
+        },
+        Some("--rule") | Some("--rule-file") => run_rule(instructions, &args),
+        Some(arg) => panic!("Unknown argument: {}", arg),
+        None => {
+            let steps_1 = day5::escape_maze_1(instructions.clone());
+            println!("Part 1 = {}", steps_1);
 
-    let steps_2 = escape_maze_2(instructions);
-    println!("Part 2 = {}", steps_2);
+            let steps_2 = day5::escape_maze_2(instructions);
+            println!("Part 2 = {}", steps_2);
+        },
+    }
 }
 
-fn parse_instructions(input: &str) -> Vec<isize> {
-    let mut instructions = Vec::new();
-    for line in input.lines() {
-        let offset = match line.parse::<isize>() {
-            Ok(n) => n,
-            Err(_) => panic!("Unable to parse input line: {}", line),
-        };
-        instructions.push(offset);
+fn run_trace(instructions: Vec<isize>, args: &[String]) {
+    let mut part = 2;
+    let mut filter = TraceFilter::all();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args.next()
+            .unwrap_or_else(|| panic!("Missing value for {}", arg));
+        match arg.as_str() {
+            "--part" => part = parse_part(value),
+            "--every" => filter.every = value.parse()
+                .unwrap_or_else(|_| panic!("Invalid step interval: {}", value)),
+            "--indices" => filter.indices = Some(trace::parse_range(value)
+                .unwrap_or_else(|| panic!("Invalid index range: {}", value))),
+            _ => panic!("Unknown trace option: {}", arg),
+        }
     }
-    instructions
-}
 
-fn escape_maze_1(mut instructions: Vec<isize>) -> usize {
-    escape_maze_impl(instructions, |n| n + 1)
+    let stdout = io::stdout();
+    let mut writer = TraceWriter::new(stdout.lock(), filter);
+    let steps = match part {
+        1 => day5::escape_maze_traced(instructions, advance_1, &mut writer),
+        _ => day5::escape_maze_traced(instructions, advance_2, &mut writer),
+    };
+    println!("Part {} = {}", part, steps);
 }
 
-fn escape_maze_2(mut instructions: Vec<isize>) -> usize {
-    escape_maze_impl(instructions, |n| if n >= 3 { n - 1 } else { n + 1 })
+fn run_debug(instructions: Vec<isize>, args: &[String]) {
+    let part = match args {
+        [flag, value] if flag == "--part" => parse_part(value),
+        [] => 2,
+        _ => panic!("Usage: --debug [--part 1|2]"),
+    };
+
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    match part {
+        1 => debugger::run(Maze::new(instructions, advance_1), stdin.lock(), stdout.lock()),
+        _ => debugger::run(Maze::new(instructions, advance_2), stdin.lock(), stdout.lock()),
+    }.expect("I/O error in debugger");
 }
 
-fn escape_maze_impl<F>(mut instructions: Vec<isize>, advance: F) -> usize
-    where F: Fn(isize) -> isize
-{
-    let mut steps = 0usize;
+/// The default limit on jumps for `--rule`, comfortably above the 22 million
+/// or so that Part 2 needs.
+const DEFAULT_MAX_STEPS: usize = 100_000_000;
 
-    let mut index = 0isize;
-    loop {
-        let offset = match instructions.get_mut(index as usize) {
-            Some(n) => n,
-            None => break, // All done!
-        };
+fn run_rule(instructions: Vec<isize>, args: &[String]) {
+    let usage = "Usage: --rule <expr|preset> | --rule-file <path> [--max-steps N]";
+    let (args, max_steps) = match args {
+        [rest @ .., flag, value] if flag == "--max-steps" => (rest, value.parse()
+            .unwrap_or_else(|_| panic!("Invalid step limit: {}", value))),
+        _ => (args, DEFAULT_MAX_STEPS),
+    };
+    let rule = match args {
+        [flag, value] if flag == "--rule" => match Rule::preset(value) {
+            Some(rule) => rule,
+            None => Rule::parse(value)
+                .unwrap_or_else(|e| panic!("Invalid rule: {}", e)),
+        },
+        [flag, path] if flag == "--rule-file" => Rule::load(path)
+            .unwrap_or_else(|e| panic!("Invalid rule: {}", e)),
+        _ => panic!("{}", usage),
+    };
 
-        index += *offset;
-        *offset = advance(*offset);
-        steps += 1;
+    let advance = |n| match rule.apply(n) {
+        Some(next) => next,
+        None => panic!("Rule overflowed or divided by zero for offset {}", n),
+    };
+    match day5::escape_maze_limited(instructions, advance, max_steps) {
+        Some(steps) => println!("Steps = {}", steps),
+        None => println!("Rule did not escape the maze within {} steps", max_steps),
     }
-
-    steps
 }
 
-#[cfg(test)]
-mod test {
-    use super::*;
-
-    #[test]
-    fn test_1() {
-        let input = "0\n3\n0\n1\n-3";
-        let instructions = parse_instructions(input);
-        assert_eq!(escape_maze_1(instructions), 5);
-    }
-
-    #[test]
-    fn test_2() {
-        let input = "0\n3\n0\n1\n-3";
-        let instructions = parse_instructions(input);
-        assert_eq!(escape_maze_2(instructions), 10);
+fn parse_part(value: &str) -> u8 {
+    match value {
+        "1" => 1,
+        "2" => 2,
+        _ => panic!("Invalid part: {}", value),
     }
 }
@@ -0,0 +1,297 @@
+//! A small expression language for offset update rules.
+//!
+//! A rule is an expression over the current offset `n`, for example
+//! `n >= 3 ? n - 1 : n + 1`. It supports integer literals, parentheses,
+//! `+ - * / %`, unary minus, the comparisons `== != < <= > >=` (which
+//! evaluate to 1 or 0) and the conditional operator `c ? a : b`.
+
+use std::fmt;
+use std::fs;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+/// The rules the two puzzle parts use, by name.
+pub const PRESETS: &[(&str, &str)] = &[
+    ("part1", "n + 1"),
+    ("part2", "n >= 3 ? n - 1 : n + 1"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Offset,
+    Literal(isize),
+    Neg(Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+    Cond(Box<Expr>, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Expr {
+    fn eval(&self, n: isize) -> Option<isize> {
+        match *self {
+            Expr::Offset => Some(n),
+            Expr::Literal(value) => Some(value),
+            Expr::Neg(ref e) => e.eval(n)?.checked_neg(),
+            Expr::Binary(op, ref l, ref r) => {
+                let (l, r) = (l.eval(n)?, r.eval(n)?);
+                match op {
+                    BinOp::Add => l.checked_add(r),
+                    BinOp::Sub => l.checked_sub(r),
+                    BinOp::Mul => l.checked_mul(r),
+                    BinOp::Div => l.checked_div(r),
+                    BinOp::Rem => l.checked_rem(r),
+                    BinOp::Eq => Some((l == r) as isize),
+                    BinOp::Ne => Some((l != r) as isize),
+                    BinOp::Lt => Some((l < r) as isize),
+                    BinOp::Le => Some((l <= r) as isize),
+                    BinOp::Gt => Some((l > r) as isize),
+                    BinOp::Ge => Some((l >= r) as isize),
+                }
+            },
+            Expr::Cond(ref c, ref a, ref b) =>
+                if c.eval(n)? != 0 { a.eval(n) } else { b.eval(n) },
+        }
+    }
+}
+
+/// An offset update rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    expr: Expr,
+}
+
+impl Rule {
+    pub fn parse(source: &str) -> Result<Rule, ParseError> {
+        let mut parser = Parser {
+            chars: source.char_indices().peekable(),
+            len: source.len(),
+        };
+        let expr = parser.expr()?;
+        parser.skip_whitespace();
+        match parser.chars.next() {
+            Some((pos, ch)) => Err(ParseError::new(pos, format!("unexpected '{}'", ch))),
+            None => Ok(Rule { expr }),
+        }
+    }
+
+    pub fn preset(name: &str) -> Option<Rule> {
+        PRESETS.iter()
+            .find(|&&(preset, _)| preset == name)
+            .map(|&(_, source)| Rule::parse(source).unwrap())
+    }
+
+    /// Reads a rule from a file. Everything after a `#` on a line is a
+    /// comment, and the remaining lines are joined into a single expression.
+    pub fn load(path: &str) -> Result<Rule, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read {}: {}", path, e))?;
+        let source = contents.lines()
+            .map(|line| line.split('#').next().unwrap())
+            .collect::<Vec<_>>()
+            .join(" ");
+        Rule::parse(&source).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Applies the rule to an offset, or returns `None` if it overflows or
+    /// divides by zero.
+    pub fn apply(&self, n: isize) -> Option<isize> {
+        self.expr.eval(n)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub pos: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    fn new(pos: usize, message: String) -> ParseError {
+        ParseError { pos, message }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.pos)
+    }
+}
+
+struct Parser<'s> {
+    chars: Peekable<CharIndices<'s>>,
+    len: usize,
+}
+
+impl<'s> Parser<'s> {
+    fn skip_whitespace(&mut self) {
+        while let Some(&(_, ch)) = self.chars.peek() {
+            if !ch.is_whitespace() {
+                break;
+            }
+            self.chars.next();
+        }
+    }
+
+    fn pos(&mut self) -> usize {
+        self.chars.peek().map_or(self.len, |&(pos, _)| pos)
+    }
+
+    /// Consumes `token` if it comes next.
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        let mut lookahead = self.chars.clone();
+        for expected in token.chars() {
+            match lookahead.next() {
+                Some((_, ch)) if ch == expected => (),
+                _ => return false,
+            }
+        }
+        self.chars = lookahead;
+        true
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), ParseError> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            let pos = self.pos();
+            Err(ParseError::new(pos, format!("expected '{}'", token)))
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, ParseError> {
+        let cond = self.comparison()?;
+        if !self.eat("?") {
+            return Ok(cond);
+        }
+        let a = self.expr()?;
+        self.expect(":")?;
+        let b = self.expr()?;
+        Ok(Expr::Cond(Box::new(cond), Box::new(a), Box::new(b)))
+    }
+
+    fn comparison(&mut self) -> Result<Expr, ParseError> {
+        let left = self.sum()?;
+        // Longer operators must be tried first
+        let ops = [
+            ("==", BinOp::Eq),
+            ("!=", BinOp::Ne),
+            ("<=", BinOp::Le),
+            (">=", BinOp::Ge),
+            ("<", BinOp::Lt),
+            (">", BinOp::Gt),
+        ];
+        for &(token, op) in ops.iter() {
+            if self.eat(token) {
+                let right = self.sum()?;
+                return Ok(Expr::Binary(op, Box::new(left), Box::new(right)));
+            }
+        }
+        Ok(left)
+    }
+
+    fn sum(&mut self) -> Result<Expr, ParseError> {
+        let mut left = self.term()?;
+        loop {
+            let op = if self.eat("+") {
+                BinOp::Add
+            } else if self.eat("-") {
+                BinOp::Sub
+            } else {
+                return Ok(left);
+            };
+            let right = self.term()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, ParseError> {
+        let mut left = self.unary()?;
+        loop {
+            let op = if self.eat("*") {
+                BinOp::Mul
+            } else if self.eat("/") {
+                BinOp::Div
+            } else if self.eat("%") {
+                BinOp::Rem
+            } else {
+                return Ok(left);
+            };
+            let right = self.unary()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        if self.eat("-") {
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Expr, ParseError> {
+        if self.eat("(") {
+            let expr = self.expr()?;
+            self.expect(")")?;
+            return Ok(expr);
+        }
+        if self.eat("n") {
+            return Ok(Expr::Offset);
+        }
+
+        let start = self.pos();
+        let mut digits = String::new();
+        while let Some(&(_, ch)) = self.chars.peek() {
+            if !ch.is_ascii_digit() {
+                break;
+            }
+            digits.push(ch);
+            self.chars.next();
+        }
+        if digits.is_empty() {
+            return Err(ParseError::new(start, "expected 'n', a number or '('".to_string()));
+        }
+        digits.parse()
+            .map(Expr::Literal)
+            .map_err(|_| ParseError::new(start, format!("number too large: {}", digits)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_presets() {
+        let part1 = Rule::preset("part1").unwrap();
+        let part2 = Rule::preset("part2").unwrap();
+        for n in -10..10 {
+            assert_eq!(part1.apply(n), Some(::advance_1(n)));
+            assert_eq!(part2.apply(n), Some(::advance_2(n)));
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let rule = Rule::parse("(n % 2 == 0) ? -n * 2 : n / 2 + 1").unwrap();
+        assert_eq!(rule.apply(4), Some(-8));
+        assert_eq!(rule.apply(5), Some(3));
+        assert_eq!(Rule::parse("n / 0").unwrap().apply(1), None);
+        assert_eq!(Rule::parse("n +").unwrap_err().pos, 3);
+        assert_eq!(Rule::parse("n ? 1").unwrap_err().message, "expected ':'");
+    }
+}
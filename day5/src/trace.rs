@@ -0,0 +1,109 @@
+use std::fmt;
+use std::io::Write;
+use std::ops::Range;
+
+/// A single jump through the maze.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Jump {
+    /// The 1-based step number of this jump.
+    pub step: usize,
+    /// The index the jump was made from.
+    pub index: usize,
+    /// The offset at `index` before the jump.
+    pub before: isize,
+    /// The offset at `index` after the jump.
+    pub after: isize,
+}
+
+impl fmt::Display for Jump {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "step {}: [{}] {} -> {}", self.step, self.index, self.before, self.after)
+    }
+}
+
+pub trait Tracer {
+    fn jump(&mut self, jump: &Jump);
+}
+
+/// Decides which jumps are worth reporting.
+#[derive(Debug, Clone)]
+pub struct TraceFilter {
+    /// Only report every Nth step. 0 and 1 both mean every step.
+    pub every: usize,
+    /// Only report jumps made from an index in this range.
+    pub indices: Option<Range<usize>>,
+}
+
+impl TraceFilter {
+    pub fn all() -> TraceFilter {
+        TraceFilter {
+            every: 1,
+            indices: None,
+        }
+    }
+
+    pub fn matches(&self, jump: &Jump) -> bool {
+        if self.every > 1 && !jump.step.is_multiple_of(self.every) {
+            return false;
+        }
+        match self.indices {
+            Some(ref range) => range.start <= jump.index && jump.index < range.end,
+            None => true,
+        }
+    }
+}
+
+/// Parses an index range of the form `start..end`, where either end may be
+/// omitted.
+pub fn parse_range(s: &str) -> Option<Range<usize>> {
+    let (start, end) = s.split_once("..")?;
+    let start = if start.is_empty() { 0 } else { start.parse().ok()? };
+    let end = if end.is_empty() { usize::MAX } else { end.parse().ok()? };
+    Some(start..end)
+}
+
+/// Records every matching jump in memory.
+pub struct TraceLog {
+    pub filter: TraceFilter,
+    pub jumps: Vec<Jump>,
+}
+
+impl TraceLog {
+    pub fn new(filter: TraceFilter) -> TraceLog {
+        TraceLog {
+            filter,
+            jumps: Vec::new(),
+        }
+    }
+}
+
+impl Tracer for TraceLog {
+    fn jump(&mut self, jump: &Jump) {
+        if self.filter.matches(jump) {
+            self.jumps.push(*jump);
+        }
+    }
+}
+
+/// Streams every matching jump to a writer, one line per jump.
+pub struct TraceWriter<W: Write> {
+    out: W,
+    filter: TraceFilter,
+}
+
+impl<W: Write> TraceWriter<W> {
+    pub fn new(out: W, filter: TraceFilter) -> TraceWriter<W> {
+        TraceWriter {
+            out,
+            filter,
+        }
+    }
+}
+
+impl<W: Write> Tracer for TraceWriter<W> {
+    fn jump(&mut self, jump: &Jump) {
+        if self.filter.matches(jump) {
+            writeln!(self.out, "{}", jump).expect("Unable to write trace");
+        }
+    }
+}
@@ -0,0 +1,164 @@
+//! Generic cycle detection for iterated functions.
+//!
+//! Given a start state `x0` and a step function `f`, the sequence
+//! `x0, f(x0), f(f(x0)), ...` eventually repeats. `mu` is the index of the
+//! first state that's part of the cycle and `lambda` is the cycle length.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CycleInfo {
+    pub mu: usize,
+    pub lambda: usize,
+}
+
+impl CycleInfo {
+    /// The number of steps taken before a state is seen for the second time.
+    pub fn first_repeat(&self) -> usize {
+        self.mu + self.lambda
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Remembers every state. Fastest, but uses memory for each state.
+    Hash,
+    /// Floyd's tortoise and hare. Constant memory.
+    Floyd,
+    /// Brent's algorithm. Constant memory, usually fewer steps than Floyd.
+    Brent,
+}
+
+impl Strategy {
+    pub fn parse(name: &str) -> Option<Strategy> {
+        match name {
+            "hash" => Some(Strategy::Hash),
+            "floyd" => Some(Strategy::Floyd),
+            "brent" => Some(Strategy::Brent),
+            _ => None,
+        }
+    }
+}
+
+/// Finds the cycle in the sequence starting at `start` using the given
+/// strategy.
+pub fn find_cycle<S, F>(start: S, step: F, strategy: Strategy) -> CycleInfo
+    where S: Clone + Eq + Hash, F: Fn(&S) -> S
+{
+    match strategy {
+        Strategy::Hash => find_cycle_hash(start, step),
+        Strategy::Floyd => find_cycle_floyd(start, step),
+        Strategy::Brent => find_cycle_brent(start, step),
+    }
+}
+
+pub fn find_cycle_hash<S, F>(start: S, step: F) -> CycleInfo
+    where S: Clone + Eq + Hash, F: Fn(&S) -> S
+{
+    find_cycle_hash_with(start, step, |_| ())
+}
+
+/// Like `find_cycle_hash`, but calls `visit` with every state in order, up
+/// to and including the first repeated one.
+pub fn find_cycle_hash_with<S, F, V>(start: S, step: F, mut visit: V) -> CycleInfo
+    where S: Clone + Eq + Hash, F: Fn(&S) -> S, V: FnMut(&S)
+{
+    let mut seen = HashMap::new();
+    let mut state = start;
+    let mut index = 0usize;
+    loop {
+        visit(&state);
+        if let Some(&first) = seen.get(&state) {
+            return CycleInfo {
+                mu: first,
+                lambda: index - first,
+            };
+        }
+        let next = step(&state);
+        seen.insert(state, index);
+        state = next;
+        index += 1;
+    }
+}
+
+pub fn find_cycle_floyd<S, F>(start: S, step: F) -> CycleInfo
+    where S: Clone + Eq, F: Fn(&S) -> S
+{
+    // Find a point inside the cycle: the hare moves twice as fast as the
+    // tortoise, so they meet once both are in the cycle.
+    let mut tortoise = step(&start);
+    let mut hare = step(&tortoise);
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&step(&hare));
+    }
+
+    // The distance from the start to the cycle equals the distance from the
+    // meeting point to the cycle start, going around the cycle.
+    let mut mu = 0;
+    let mut tortoise = start;
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        mu += 1;
+    }
+
+    let mut lambda = 1;
+    let mut hare = step(&tortoise);
+    while tortoise != hare {
+        hare = step(&hare);
+        lambda += 1;
+    }
+
+    CycleInfo { mu, lambda }
+}
+
+pub fn find_cycle_brent<S, F>(start: S, step: F) -> CycleInfo
+    where S: Clone + Eq, F: Fn(&S) -> S
+{
+    // Find the cycle length by searching successive powers of two.
+    let mut power = 1;
+    let mut lambda = 1;
+    let mut tortoise = start.clone();
+    let mut hare = step(&start);
+    while tortoise != hare {
+        if power == lambda {
+            tortoise = hare.clone();
+            power *= 2;
+            lambda = 0;
+        }
+        hare = step(&hare);
+        lambda += 1;
+    }
+
+    // With the hare `lambda` steps ahead, both meet at the cycle start.
+    let mut tortoise = start.clone();
+    let mut hare = start;
+    for _ in 0..lambda {
+        hare = step(&hare);
+    }
+    let mut mu = 0;
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        mu += 1;
+    }
+
+    CycleInfo { mu, lambda }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_strategies_agree() {
+        // 0 -> 1 -> ... -> 6 -> 3 (mu = 3, lambda = 4)
+        let step = |&n: &u32| if n == 6 { 3 } else { n + 1 };
+        let expected = CycleInfo { mu: 3, lambda: 4 };
+        assert_eq!(find_cycle(0, step, Strategy::Hash), expected);
+        assert_eq!(find_cycle(0, step, Strategy::Floyd), expected);
+        assert_eq!(find_cycle(0, step, Strategy::Brent), expected);
+    }
+}
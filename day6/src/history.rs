@@ -0,0 +1,141 @@
+//! Recording and exporting the sequence of bank configurations.
+
+use std::fmt::Display;
+use std::io::{self, Write};
+
+use super::{balance_memory, Block};
+use cycle::find_cycle_hash_with;
+
+/// Characters used for the heatmap, from emptiest to fullest.
+const SHADES: &[u8] = b" .:-=+*#%@";
+
+/// Every configuration the banks go through until one repeats.
+pub struct History<T> {
+    /// The initial configuration followed by the result of each
+    /// redistribution, ending with the first repeated configuration.
+    pub states: Vec<Vec<T>>,
+    /// The index of the first configuration that is part of the cycle.
+    pub cycle_start: usize,
+}
+
+impl<T: Block> History<T> {
+    pub fn record(banks: &[T]) -> History<T> {
+        let mut states = Vec::new();
+        let step = |state: &Vec<T>| {
+            let mut next = state.clone();
+            balance_memory(&mut next);
+            next
+        };
+        let info = find_cycle_hash_with(banks.to_vec(), step, |state| states.push(state.clone()));
+        History {
+            states,
+            cycle_start: info.mu,
+        }
+    }
+
+    /// The index of the first repeated configuration, which is the last one.
+    pub fn repeat(&self) -> usize {
+        self.states.len() - 1
+    }
+
+    fn mark(&self, index: usize) -> &'static str {
+        if index == self.cycle_start {
+            "cycle start"
+        } else if index == self.repeat() {
+            "first repeat"
+        } else {
+            ""
+        }
+    }
+}
+
+impl<T: Block + Display> History<T> {
+    pub fn write_csv<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        write!(out, "step")?;
+        for bank in 0..self.states[0].len() {
+            write!(out, ",bank{}", bank)?;
+        }
+        writeln!(out, ",mark")?;
+
+        for (index, state) in self.states.iter().enumerate() {
+            write!(out, "{}", index)?;
+            for value in state.iter() {
+                write!(out, ",{}", value)?;
+            }
+            writeln!(out, ",{}", self.mark(index))?;
+        }
+        Ok(())
+    }
+
+    /// Writes one row of characters per configuration, with fuller banks
+    /// drawn with denser characters.
+    pub fn write_heatmap<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let max = self.states.iter()
+            .flat_map(|state| state.iter())
+            .max()
+            .cloned()
+            .unwrap_or(T::ZERO);
+        let width = self.repeat().to_string().len();
+
+        for (index, state) in self.states.iter().enumerate() {
+            let row: String = state.iter()
+                .map(|&value| SHADES[shade(value, max)] as char)
+                .collect();
+            let mark = self.mark(index);
+            if mark.is_empty() {
+                writeln!(out, "{:>w$} |{}|", index, row, w = width)?;
+            } else {
+                writeln!(out, "{:>w$} |{}| <- {}", index, row, mark, w = width)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Picks the index into `SHADES` for `value` out of `max`.
+fn shade<T: Block>(value: T, max: T) -> usize {
+    if max == T::ZERO {
+        return 0;
+    }
+    let levels = (SHADES.len() - 1) as f64;
+    let fraction = value.to_u128() as f64 / max.to_u128() as f64;
+    (fraction * levels).round() as usize
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let history = History::record(&[0u8, 2, 7, 0]);
+        assert_eq!(history.cycle_start, 1);
+        assert_eq!(history.repeat(), 5);
+        assert_eq!(history.states[1], [2, 4, 1, 2]);
+        assert_eq!(history.states[5], history.states[1]);
+
+        let mut csv = Vec::new();
+        history.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "step,bank0,bank1,bank2,bank3,mark");
+        assert_eq!(lines[2], "1,2,4,1,2,cycle start");
+        assert_eq!(lines[6], "5,2,4,1,2,first repeat");
+    }
+
+    #[test]
+    fn test_heatmap() {
+        let history = History::record(&[0u8, 2, 7, 0]);
+        let mut heatmap = Vec::new();
+        history.write_heatmap(&mut heatmap).unwrap();
+        let heatmap = String::from_utf8(heatmap).unwrap();
+        assert_eq!(heatmap.lines().collect::<Vec<_>>(), vec![
+            "0 | -@ |",
+            "1 |-+.-| <- cycle start",
+            "2 |=.-=|",
+            "3 | -=+|",
+            "4 |.=+.|",
+            "5 |-+.-| <- first repeat",
+        ]);
+    }
+}
@@ -1,68 +1,181 @@
-use std::collections::{HashSet, HashMap};
-
-macro_rules! detect_loop {
-    ($banks:expr) => {{
-        let mut cycles = 0usize;
-        let mut seen = HashSet::new();
-        loop {
-            cycles += 1;
-            balance_memory(&mut $banks);
-            if !seen.insert($banks.clone()) {
-                break;
-            }
-        }
-        cycles
-    }}
+mod cycle;
+mod history;
+
+use std::env;
+use std::fs;
+use std::io;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use cycle::{find_cycle, CycleInfo, Strategy};
+use history::History;
+
+/// An unsigned integer type that bank block counts can be stored as.
+trait Block: Copy + Ord + Hash + Debug {
+    const ZERO: Self;
+
+    /// Splits `self` blocks evenly between `n` banks, returning the share of
+    /// each bank and the number of blocks left over.
+    fn share(self, n: usize) -> (Self, usize);
+    fn checked_add(self, other: Self) -> Option<Self>;
+    fn incremented(self) -> Option<Self>;
+    fn to_u128(self) -> u128;
 }
 
-macro_rules! count_cycles {
-    ($banks:expr) => {{
-        let mut cycle = 0usize;
-        let mut seen = HashMap::new();
-        let mut result = 0usize;
-        loop {
-            cycle += 1;
-            balance_memory(&mut $banks);
-            if seen.contains_key(&$banks) {
-                result = cycle - seen[&$banks];
-                break;
-            } else {
-                seen.insert($banks.clone(), cycle);
+macro_rules! impl_block {
+    ($($t:ty),*) => {$(
+        impl Block for $t {
+            const ZERO: $t = 0;
+
+            fn share(self, n: usize) -> ($t, usize) {
+                let (value, n) = (self as u128, n as u128);
+                ((value / n) as $t, (value % n) as usize)
+            }
+
+            fn checked_add(self, other: $t) -> Option<$t> {
+                <$t>::checked_add(self, other)
+            }
+
+            fn incremented(self) -> Option<$t> {
+                <$t>::checked_add(self, 1)
+            }
+
+            fn to_u128(self) -> u128 {
+                self as u128
             }
         }
-        result
-    }}
+    )*}
+}
+
+impl_block!(u8, u16, u32, u64, u128, usize);
+
+/// Finds the loop in the sequence of bank configurations produced by
+/// repeatedly balancing `banks`.
+fn find_loop<T: Block>(banks: &[T], strategy: Strategy) -> CycleInfo {
+    find_cycle(banks.to_vec(), |banks| {
+        let mut next = banks.clone();
+        balance_memory(&mut next);
+        next
+    }, strategy)
 }
 
-fn balance_memory(banks: &mut [u8]) {
-    let (mut i, mut value) = banks.iter()
+/// Redistributes the blocks of the fullest bank across all banks.
+///
+/// Panics if a bank ends up with more blocks than `T` can hold, which can
+/// only happen if the total number of blocks doesn't fit in a `T`.
+fn balance_memory<T: Block>(banks: &mut [T]) {
+    let (i, value) = banks.iter()
         .enumerate()
-        .fold((0, 0), |(max_idx, max_val), (idx, &val)|
+        .fold((0, T::ZERO), |(max_idx, max_val), (idx, &val)|
             if val > max_val {
                 (idx, val)
             } else {
                 (max_idx, max_val)
             });
     // Empty the starting bank
-    banks[i] = 0;
-    while value > 0 {
-        // Advance to the next bank
-        i = (i + 1) % banks.len();
-        banks[i] += 1;
-        value -= 1;
+    banks[i] = T::ZERO;
+
+    // Every bank gets an equal share, and the remainder goes one block at a
+    // time to the banks following the starting bank.
+    let len = banks.len();
+    let (share, remainder) = value.share(len);
+    for k in 1..=len {
+        let bank = &mut banks[(i + k) % len];
+        let mut new_value = bank.checked_add(share);
+        if k <= remainder {
+            new_value = new_value.and_then(Block::incremented);
+        }
+        *bank = new_value.unwrap_or_else(|| panic!("Bank {} overflowed", (i + k) % len));
     }
 }
 
+/// Parses a bank configuration, with banks separated by tabs, commas or
+/// spaces.
+fn parse_banks(line: &str) -> Result<Vec<u64>, String> {
+    let banks = line.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<u64>().map_err(|_| format!("Invalid bank: {}", s)))
+        .collect::<Result<Vec<_>, _>>()?;
+    if banks.is_empty() {
+        return Err(format!("No banks in configuration: {}", line));
+    }
+    Ok(banks)
+}
+
+/// Reads one bank configuration per line, skipping blank lines and `#`
+/// comments.
+fn read_banks_file(path: &str) -> Vec<Vec<u64>> {
+    let contents = fs::read_to_string(path)
+        .unwrap_or_else(|e| panic!("Unable to read {}: {}", path, e));
+    contents.lines()
+        .map(|line| line.split('#').next().unwrap().trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| parse_banks(line).unwrap_or_else(|e| panic!("{}: {}", path, e)))
+        .collect()
+}
+
+fn format_banks(banks: &[u64]) -> String {
+    banks.iter()
+        .map(|n| n.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
 fn main() {
-    let input = [10, 3, 15, 10, 5, 15, 5, 15, 9, 2, 5, 8, 5, 2, 3, 6];
+    let mut configs = Vec::new();
+    let mut strategy = Strategy::Hash;
+    let mut history_format = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--banks" => {
+                let banks = args.next().expect("Missing value for --banks");
+                configs.push(parse_banks(&banks).unwrap_or_else(|e| panic!("{}", e)));
+            },
+            "--file" => {
+                let path = args.next().expect("Missing value for --file");
+                configs.extend(read_banks_file(&path));
+            },
+            "--history" => {
+                history_format = Some(args.next().expect("Missing value for --history"));
+            },
+            _ => strategy = Strategy::parse(&arg)
+                .unwrap_or_else(|| panic!("Unknown strategy: {}", arg)),
+        }
+    }
+    if configs.is_empty() {
+        configs.push(vec![10, 3, 15, 10, 5, 15, 5, 15, 9, 2, 5, 8, 5, 2, 3, 6]);
+    }
 
-    let mut banks = input.clone();
-    let part1 = detect_loop!(banks);
-    println!("Part 1 = {}", part1);
+    if let Some(format) = history_format {
+        if configs.len() != 1 {
+            panic!("--history needs exactly one bank configuration");
+        }
+        let history = History::record(&configs[0]);
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+        match format.as_str() {
+            "csv" => history.write_csv(&mut out),
+            "heatmap" => history.write_heatmap(&mut out),
+            _ => panic!("Usage: --history csv|heatmap"),
+        }.expect("Unable to write history");
+        return;
+    }
 
-    let mut banks = input.clone();
-    let part2 = count_cycles!(banks);
-    println!("Part 2 = {}", part2);
+    if configs.len() == 1 {
+        let info = find_loop(&configs[0], strategy);
+        println!("Part 1 = {}", info.first_repeat());
+        println!("Part 2 = {}", info.lambda);
+        return;
+    }
+
+    let names: Vec<String> = configs.iter().map(|banks| format_banks(banks)).collect();
+    let width = names.iter().map(|name| name.len()).max().unwrap().max(5);
+    println!("{:<w$}  {:>10}  {:>10}", "banks", "part 1", "part 2", w = width);
+    for (banks, name) in configs.iter().zip(names.iter()) {
+        let info = find_loop(banks, strategy);
+        println!("{:<w$}  {:>10}  {:>10}", name, info.first_repeat(), info.lambda, w = width);
+    }
 }
 
 #[cfg(test)]
@@ -71,29 +184,60 @@ mod test {
 
     #[test]
     fn test_balance1() {
-        let mut banks = [1, 2, 3];
+        let mut banks = [1u8, 2, 3];
         balance_memory(&mut banks);
         assert_eq!(banks, [2, 3, 1]);
     }
 
     #[test]
     fn test_balance2() {
-        let mut banks = [1, 3, 3];
+        let mut banks = [1u8, 3, 3];
         balance_memory(&mut banks);
         assert_eq!(banks, [2, 1, 4]);
     }
 
+    #[test]
+    fn test_balance_wide() {
+        let mut banks = [3u64, 1_000_000_000_001, 5];
+        balance_memory(&mut banks);
+        assert_eq!(banks, [333_333_333_337, 333_333_333_333, 333_333_333_339]);
+
+        let mut banks = [1u16, 300, 0];
+        balance_memory(&mut banks);
+        assert_eq!(banks, [101, 100, 100]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Bank 1 overflowed")]
+    fn test_balance_overflow() {
+        let mut banks = [200u8, 200];
+        balance_memory(&mut banks);
+    }
+
+    #[test]
+    fn test_parse_banks() {
+        assert_eq!(parse_banks("0\t2\t7\t0"), Ok(vec![0, 2, 7, 0]));
+        assert_eq!(parse_banks("0, 2,7 ,0"), Ok(vec![0, 2, 7, 0]));
+        assert!(parse_banks("0,x").is_err());
+        assert!(parse_banks(" ").is_err());
+    }
+
     #[test]
     fn test_loop() {
-        let mut banks = [0, 2, 7, 0];
-        let cycles = detect_loop!(banks);
-        assert_eq!(cycles, 5);
+        let info = find_loop(&[0u8, 2, 7, 0], Strategy::Hash);
+        assert_eq!(info.first_repeat(), 5);
     }
 
     #[test]
     fn test_count() {
-        let mut banks = [0, 2, 7, 0];
-        let cycles = count_cycles!(banks);
-        assert_eq!(cycles, 4);
+        let info = find_loop(&[0u8, 2, 7, 0], Strategy::Hash);
+        assert_eq!(info.lambda, 4);
+    }
+
+    #[test]
+    fn test_constant_memory() {
+        let expected = find_loop(&[0u8, 2, 7, 0], Strategy::Hash);
+        assert_eq!(find_loop(&[0u8, 2, 7, 0], Strategy::Floyd), expected);
+        assert_eq!(find_loop(&[0u8, 2, 7, 0], Strategy::Brent), expected);
     }
 }
@@ -0,0 +1,33 @@
+//! Compares the interpreter against the compiled bytecode.
+//!
+//! Run with `cargo bench`.
+
+extern crate day8;
+
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 2000;
+
+fn time<T, F: FnMut() -> T>(mut f: F) -> (T, Duration) {
+    let start = Instant::now();
+    let mut result = f();
+    for _ in 1..ITERATIONS {
+        result = f();
+    }
+    (result, start.elapsed())
+}
+
+fn main() {
+    let input = include_str!("../src/input.txt");
+    let instructions = day8::parse_instructions(input);
+    let program = day8::Program::compile(&instructions);
+
+    let (expected, interpreted) = time(|| day8::run(&instructions));
+    let (actual, compiled) = time(|| program.run());
+    assert_eq!((expected.0 as i64, expected.1 as i64), actual);
+
+    println!("{} runs of {} instructions", ITERATIONS, instructions.len());
+    println!("interpreter: {:?}", interpreted);
+    println!("bytecode:    {:?}", compiled);
+    println!("speedup:     {:.1}x", interpreted.as_secs_f64() / compiled.as_secs_f64());
+}
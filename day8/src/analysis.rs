@@ -0,0 +1,198 @@
+//! Static analysis of programs, without running them.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt;
+use std::io::{self, Write};
+
+use super::{Condition, Instruction, Operand};
+
+pub struct Analysis<'s> {
+    /// Registers that are written but never used in a condition.
+    pub written_never_read: BTreeSet<&'s str>,
+    /// Registers that are used in a condition but never written, and are
+    /// therefore always 0.
+    pub read_never_written: BTreeSet<&'s str>,
+    /// Instructions whose condition always has the same value, with that
+    /// value.
+    pub constant_conditions: Vec<(usize, bool)>,
+    /// The number of instructions that can write to each register.
+    pub write_counts: BTreeMap<&'s str, usize>,
+    /// Pairs `(a, b)` where the value of `a` decides whether `b` is written.
+    pub dependencies: BTreeSet<(&'s str, &'s str)>,
+}
+
+fn condition_registers<'s>(condition: &Condition<'s>, regs: &mut Vec<&'s str>) {
+    match *condition {
+        Condition::Compare(left, _, right) => {
+            for operand in [left, right].iter() {
+                if let Operand::Reg(reg) = *operand {
+                    regs.push(reg);
+                }
+            }
+        },
+        Condition::And(ref a, ref b) | Condition::Or(ref a, ref b) => {
+            condition_registers(a, regs);
+            condition_registers(b, regs);
+        },
+    }
+}
+
+/// Evaluates a condition if its value doesn't depend on register contents.
+/// Registers in `unwritten` are known to still be 0.
+fn constant_value(condition: &Condition, unwritten: &dyn Fn(&str) -> bool) -> Option<bool> {
+    let operand_value = |operand: Operand| match operand {
+        Operand::Value(value) => Some(value),
+        Operand::Reg(reg) if unwritten(reg) => Some(0),
+        Operand::Reg(_) => None,
+    };
+
+    match *condition {
+        Condition::Compare(left, comparison, right) => {
+            if left == right {
+                // Comparing a register against itself
+                return Some(comparison.test(0, 0));
+            }
+            let left = operand_value(left)?;
+            let right = operand_value(right)?;
+            Some(comparison.test(left, right))
+        },
+        Condition::And(ref a, ref b) => match (constant_value(a, unwritten), constant_value(b, unwritten)) {
+            (Some(false), _) | (_, Some(false)) => Some(false),
+            (Some(true), Some(true)) => Some(true),
+            _ => None,
+        },
+        Condition::Or(ref a, ref b) => match (constant_value(a, unwritten), constant_value(b, unwritten)) {
+            (Some(true), _) | (_, Some(true)) => Some(true),
+            (Some(false), Some(false)) => Some(false),
+            _ => None,
+        },
+    }
+}
+
+pub fn analyze<'s>(instructions: &[Instruction<'s>]) -> Analysis<'s> {
+    let mut read = BTreeSet::new();
+    let mut written = HashSet::new();
+    let mut write_counts = BTreeMap::new();
+    let mut constant_conditions = Vec::new();
+    let mut dependencies = BTreeSet::new();
+
+    for (index, instr) in instructions.iter().enumerate() {
+        // The program is straight-line code, so anything not written by an
+        // earlier instruction is still 0 here.
+        if let Some(value) = constant_value(&instr.condition, &|reg| !written.contains(reg)) {
+            constant_conditions.push((index, value));
+        }
+
+        let mut regs = Vec::new();
+        condition_registers(&instr.condition, &mut regs);
+        let targets = Some(&instr.operation).into_iter().chain(instr.otherwise.as_ref());
+        for operation in targets {
+            *write_counts.entry(operation.reg).or_insert(0) += 1;
+            for &reg in regs.iter() {
+                if reg != operation.reg {
+                    dependencies.insert((reg, operation.reg));
+                }
+            }
+            written.insert(operation.reg);
+        }
+        read.extend(regs);
+    }
+
+    Analysis {
+        written_never_read: write_counts.keys()
+            .filter(|reg| !read.contains(*reg))
+            .cloned()
+            .collect(),
+        read_never_written: read.iter()
+            .filter(|reg| !written.contains(*reg))
+            .cloned()
+            .collect(),
+        constant_conditions,
+        write_counts,
+        dependencies,
+    }
+}
+
+impl<'s> Analysis<'s> {
+    /// Writes the register dependency graph in Graphviz DOT format, with each
+    /// register labelled by its write count. Register names are quoted, since
+    /// some of them (`node`, `edge`, `graph`...) are DOT keywords.
+    pub fn write_dot<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "digraph registers {{")?;
+        for (reg, count) in self.write_counts.iter() {
+            writeln!(out, "    \"{}\" [label=\"{} ({})\"];", reg, reg, count)?;
+        }
+        for reg in self.read_never_written.iter() {
+            writeln!(out, "    \"{}\" [label=\"{} (0)\", style=dashed];", reg, reg)?;
+        }
+        for &(from, to) in self.dependencies.iter() {
+            writeln!(out, "    \"{}\" -> \"{}\";", from, to)?;
+        }
+        writeln!(out, "}}")
+    }
+}
+
+fn join(regs: &BTreeSet<&str>) -> String {
+    if regs.is_empty() {
+        return "(none)".to_string();
+    }
+    regs.iter().cloned().collect::<Vec<_>>().join(", ")
+}
+
+impl<'s> fmt::Display for Analysis<'s> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Written but never read: {}", join(&self.written_never_read))?;
+        writeln!(f, "Read but never written: {}", join(&self.read_never_written))?;
+        writeln!(f, "Constant conditions:")?;
+        for &(index, value) in self.constant_conditions.iter() {
+            writeln!(f, "    instruction {}: always {}", index, value)?;
+        }
+        writeln!(f, "Write counts:")?;
+        for (reg, count) in self.write_counts.iter() {
+            writeln!(f, "    {}: {}", reg, count)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::parse_instructions;
+
+    #[test]
+    fn test_analyze() {
+        let input = "a inc 1 if x == 0\n\
+                     b inc 1 if a > 0 and 1 < 2\n\
+                     c inc 1 if b >= b or a > 5\n\
+                     d dec 2 if x != 0 else a inc 3\n\
+                     x inc 1 if c > 1 and e == 0";
+        let analysis = analyze(&parse_instructions(input));
+        let set = |regs: &[&'static str]| regs.iter().cloned().collect::<BTreeSet<_>>();
+        assert_eq!(analysis.written_never_read, set(&["d"]));
+        assert_eq!(analysis.read_never_written, set(&["e"]));
+        assert_eq!(analysis.constant_conditions, vec![(0, true), (2, true), (3, false)]);
+        assert_eq!(analysis.write_counts["a"], 2);
+        assert!(analysis.dependencies.contains(&("x", "d")));
+        assert!(analysis.dependencies.contains(&("a", "b")));
+    }
+
+    #[test]
+    fn test_dot_keywords() {
+        let input = "node inc 1 if edge > 0\n\
+                     graph inc 1 if node > 0";
+        let analysis = analyze(&parse_instructions(input));
+        let mut dot = Vec::new();
+        analysis.write_dot(&mut dot).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+        assert_eq!(dot.lines().collect::<Vec<_>>(), vec![
+            "digraph registers {",
+            "    \"graph\" [label=\"graph (1)\"];",
+            "    \"node\" [label=\"node (1)\"];",
+            "    \"edge\" [label=\"edge (0)\", style=dashed];",
+            "    \"edge\" -> \"node\";",
+            "    \"node\" -> \"graph\";",
+            "}",
+        ]);
+    }
+}
@@ -0,0 +1,204 @@
+//! A minimal arbitrary-precision integer, supporting just what registers
+//! need: arithmetic with machine-sized amounts, comparison and printing.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+/// A signed integer of unbounded size.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct BigInt {
+    negative: bool,
+    /// Base 2^32 digits, least significant first, without trailing zeros.
+    /// Zero has no digits and is never negative.
+    digits: Vec<u32>,
+}
+
+fn cmp_digits(a: &[u32], b: &[u32]) -> Ordering {
+    a.len().cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_digits(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0u64;
+    for i in 0..a.len().max(b.len()) {
+        let sum = carry
+            + *a.get(i).unwrap_or(&0) as u64
+            + *b.get(i).unwrap_or(&0) as u64;
+        result.push(sum as u32);
+        carry = sum >> 32;
+    }
+    if carry > 0 {
+        result.push(carry as u32);
+    }
+    result
+}
+
+/// Computes `a - b`, where `a >= b`.
+fn sub_digits(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+    for (i, &digit) in a.iter().enumerate() {
+        let mut diff = digit as i64 - borrow - *b.get(i).unwrap_or(&0) as i64;
+        borrow = 0;
+        if diff < 0 {
+            diff += 1 << 32;
+            borrow = 1;
+        }
+        result.push(diff as u32);
+    }
+    result
+}
+
+fn mul_small(a: &[u32], n: u64) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len() + 2);
+    let mut carry = 0u128;
+    for &digit in a.iter() {
+        let product = digit as u128 * n as u128 + carry;
+        result.push(product as u32);
+        carry = product >> 32;
+    }
+    while carry > 0 {
+        result.push(carry as u32);
+        carry >>= 32;
+    }
+    result
+}
+
+/// Divides by a nonzero `n`, returning the quotient and remainder.
+fn divrem_small(a: &[u32], n: u64) -> (Vec<u32>, u64) {
+    let mut quotient = vec![0; a.len()];
+    let mut remainder = 0u128;
+    for (i, &digit) in a.iter().enumerate().rev() {
+        let current = (remainder << 32) | digit as u128;
+        quotient[i] = (current / n as u128) as u32;
+        remainder = current % n as u128;
+    }
+    (quotient, remainder as u64)
+}
+
+impl BigInt {
+    fn new(negative: bool, mut digits: Vec<u32>) -> BigInt {
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        BigInt {
+            negative: negative && !digits.is_empty(),
+            digits,
+        }
+    }
+
+    pub fn from_i128(n: i128) -> BigInt {
+        let mut magnitude = n.unsigned_abs();
+        let mut digits = Vec::new();
+        while magnitude > 0 {
+            digits.push(magnitude as u32);
+            magnitude >>= 32;
+        }
+        BigInt::new(n < 0, digits)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    pub fn neg(&self) -> BigInt {
+        BigInt::new(!self.negative, self.digits.clone())
+    }
+
+    pub fn add(&self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::new(self.negative, add_digits(&self.digits, &other.digits));
+        }
+        match cmp_digits(&self.digits, &other.digits) {
+            Ordering::Less => BigInt::new(other.negative, sub_digits(&other.digits, &self.digits)),
+            _ => BigInt::new(self.negative, sub_digits(&self.digits, &other.digits)),
+        }
+    }
+
+    pub fn sub(&self, other: &BigInt) -> BigInt {
+        self.add(&other.neg())
+    }
+
+    pub fn mul_i64(&self, n: i64) -> BigInt {
+        BigInt::new(self.negative != (n < 0), mul_small(&self.digits, n.unsigned_abs()))
+    }
+
+    /// Divides by a nonzero `n`, rounding towards zero as Rust's integer
+    /// division does. The remainder has the sign of `self`.
+    pub fn divrem_i64(&self, n: i64) -> (BigInt, BigInt) {
+        assert!(n != 0, "Division by zero");
+        let (quotient, remainder) = divrem_small(&self.digits, n.unsigned_abs());
+        (BigInt::new(self.negative != (n < 0), quotient),
+         BigInt::new(self.negative, vec![remainder as u32, (remainder >> 32) as u32]))
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &BigInt) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_digits(&self.digits, &other.digits),
+            (true, true) => cmp_digits(&other.digits, &self.digits),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &BigInt) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+
+        // Peel off 9 decimal digits at a time, least significant first
+        let mut chunks = Vec::new();
+        let mut digits = self.digits.clone();
+        while !digits.is_empty() {
+            let (quotient, remainder) = divrem_small(&digits, 1_000_000_000);
+            chunks.push(remainder);
+            digits = BigInt::new(false, quotient).digits;
+        }
+
+        if self.negative {
+            f.write_str("-")?;
+        }
+        write!(f, "{}", chunks.pop().unwrap())?;
+        for chunk in chunks.iter().rev() {
+            write!(f, "{:09}", chunk)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_arithmetic() {
+        let values = [0i128, 1, -1, 7, -7, 1 << 40, -(1 << 70) + 3, i64::MAX as i128, i64::MIN as i128];
+        let amounts = [1i64, -1, 3, -5, 1 << 33, i64::MAX, i64::MIN];
+        for &a in values.iter() {
+            let big = BigInt::from_i128(a);
+            assert_eq!(big.to_string(), a.to_string());
+            for &n in amounts.iter() {
+                let b = n as i128;
+                assert_eq!(big.add(&BigInt::from_i128(b)), BigInt::from_i128(a + b));
+                assert_eq!(big.sub(&BigInt::from_i128(b)), BigInt::from_i128(a - b));
+                if let Some(product) = a.checked_mul(b) {
+                    assert_eq!(big.mul_i64(n), BigInt::from_i128(product));
+                }
+                let (q, r) = big.divrem_i64(n);
+                assert_eq!((q, r), (BigInt::from_i128(a / b), BigInt::from_i128(a % b)));
+                assert_eq!(big.cmp(&BigInt::from_i128(b)), a.cmp(&b));
+            }
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! An interactive shell for the register language.
+//!
+//! Lines are executed as instructions as soon as they are entered. Lines
+//! starting with `:` are commands; see `:help`.
+
+extern crate day8;
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+use day8::{Instruction, Parser};
+
+const HELP: &str = "\
+Enter an instruction such as `a inc 5 if b < 1` to execute it, or a command:
+  :regs          Show all registers
+  :reset         Clear all registers and the undo history
+  :load FILE     Load a program without running it
+  :run           Run the loaded program
+  :undo          Undo the last instruction
+  :help          Show this message
+  :quit          Exit";
+
+struct Repl {
+    registers: BTreeMap<String, isize>,
+    /// For each executed instruction, the register it wrote and that
+    /// register's previous value, if it had one.
+    history: Vec<Option<(String, Option<isize>)>>,
+    program: Option<String>,
+}
+
+impl Repl {
+    fn new() -> Repl {
+        Repl {
+            registers: BTreeMap::new(),
+            history: Vec::new(),
+            program: None,
+        }
+    }
+
+    /// Executes one instruction, returning a description of what happened,
+    /// or an error if it overflowed. An instruction that overflows changes
+    /// nothing and isn't added to the undo history.
+    fn exec(&mut self, instr: &Instruction) -> Result<String, String> {
+        let mut registers: HashMap<&str, isize> = self.registers.iter()
+            .map(|(reg, &value)| (reg.as_str(), value))
+            .collect();
+        let outcome = match instr.exec_checked(&mut registers) {
+            Some(outcome) => outcome,
+            None => return Err(format!("error: overflow in `{}`, nothing changed", instr)),
+        };
+
+        let change = match outcome.change {
+            Some(change) => change,
+            None => {
+                self.history.push(None);
+                return Ok("condition false, nothing changed".to_string());
+            },
+        };
+        let message = format!("{}: {} -> {}", change.reg, change.before, change.after);
+        let reg = change.reg.to_string();
+        let previous = self.registers.insert(reg.clone(), change.after);
+        self.history.push(Some((reg, previous)));
+        Ok(message)
+    }
+
+    fn undo(&mut self) -> String {
+        match self.history.pop() {
+            None => "nothing to undo".to_string(),
+            Some(None) => "undid an instruction that changed nothing".to_string(),
+            Some(Some((reg, previous))) => {
+                match previous {
+                    Some(value) => self.registers.insert(reg.clone(), value),
+                    None => self.registers.remove(&reg),
+                };
+                format!("{} restored to {}", reg, previous.unwrap_or(0))
+            },
+        }
+    }
+
+    fn run_program<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+        let program = match self.program.take() {
+            Some(program) => program,
+            None => return writeln!(out, "no program loaded"),
+        };
+        match Parser::new().parse_program_lines(&program) {
+            Ok(instructions) => {
+                let mut executed = 0;
+                for &(line, ref instr) in instructions.iter() {
+                    if let Err(e) = self.exec(instr) {
+                        writeln!(out, "line {}: {}", line, e)?;
+                        break;
+                    }
+                    executed += 1;
+                }
+                writeln!(out, "executed {} instructions", executed)?;
+            },
+            Err(errors) => {
+                for e in errors.iter() {
+                    writeln!(out, "{}\n", e)?;
+                }
+                writeln!(out, "{} errors, nothing executed", errors.len())?;
+            },
+        }
+        self.program = Some(program);
+        self.show(out)
+    }
+
+    fn show<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        if self.registers.is_empty() {
+            return writeln!(out, "(all registers are 0)");
+        }
+        for (reg, value) in self.registers.iter() {
+            writeln!(out, "{} = {}", reg, value)?;
+        }
+        Ok(())
+    }
+
+    fn command<W: Write>(&mut self, line: &str, out: &mut W) -> io::Result<bool> {
+        let mut words = line.split_whitespace();
+        match (words.next().unwrap_or(""), words.next()) {
+            (":regs", None) => self.show(out)?,
+            (":reset", None) => {
+                *self = Repl {
+                    program: self.program.take(),
+                    ..Repl::new()
+                };
+                writeln!(out, "registers cleared")?;
+            },
+            (":load", Some(path)) => match fs::read_to_string(path) {
+                Ok(program) => {
+                    writeln!(out, "loaded {} lines", program.lines().count())?;
+                    self.program = Some(program);
+                },
+                Err(e) => writeln!(out, "unable to read {}: {}", path, e)?,
+            },
+            (":run", None) => self.run_program(out)?,
+            (":undo", None) => writeln!(out, "{}", self.undo())?,
+            (":help", None) => writeln!(out, "{}", HELP)?,
+            (":quit", None) => return Ok(false),
+            _ => writeln!(out, "unknown command: {} (try :help)", line)?,
+        }
+        Ok(true)
+    }
+}
+
+fn run<R: BufRead, W: Write>(input: R, mut out: W, prompt: bool) -> io::Result<()> {
+    let mut repl = Repl::new();
+    if prompt {
+        write!(out, "> ")?;
+        out.flush()?;
+    }
+    for line in input.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.starts_with(':') {
+            if !repl.command(line, &mut out)? {
+                break;
+            }
+        } else if !line.is_empty() {
+            match day8::parse_instruction(line) {
+                Ok(instr) => match repl.exec(&instr) {
+                    Ok(message) | Err(message) => writeln!(out, "{}", message)?,
+                },
+                Err(e) => writeln!(out, "error: {}", e)?,
+            }
+        }
+        if prompt {
+            write!(out, "> ")?;
+            out.flush()?;
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    println!("{}", HELP);
+    run(stdin.lock(), stdout.lock(), true).expect("I/O error");
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_session() {
+        let input = "a inc 5 if b < 1\n\
+                     b dec 2 if a > 10\n\
+                     b set 7 if a == 5\n\
+                     :undo\n\
+                     :undo\n\
+                     a mul\n\
+                     a set 9223372036854775807 if a == 5\n\
+                     a inc 1 if a > 0\n\
+                     :undo\n\
+                     :regs\n";
+        let mut out = Vec::new();
+        run(input.as_bytes(), &mut out, false).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.lines().collect::<Vec<_>>(), vec![
+            "a: 0 -> 5",
+            "condition false, nothing changed",
+            "b: 0 -> 7",
+            "b restored to 0",
+            "undid an instruction that changed nothing",
+            "error: line 1, column 6: expected integer, found end of line",
+            "1 | a mul",
+            "  |      ^",
+            "a: 5 -> 9223372036854775807",
+            "error: overflow in `a inc 1 if a > 0`, nothing changed",
+            "a restored to 5",
+            "a = 5",
+        ]);
+    }
+
+    #[test]
+    fn test_run_overflow() {
+        let mut repl = Repl::new();
+        repl.program = Some("# Overflows on line 4\n\
+                             \n\
+                             a set 9223372036854775807 if a == 0\n\
+                             a inc 1 if a > 0\n".to_string());
+        let mut out = Vec::new();
+        repl.run_program(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.lines().collect::<Vec<_>>(), vec![
+            "line 4: error: overflow in `a inc 1 if a > 0`, nothing changed",
+            "executed 1 instructions",
+            "a = 9223372036854775807",
+        ]);
+    }
+}
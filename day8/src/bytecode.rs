@@ -0,0 +1,238 @@
+//! Compiles instructions into a flat bytecode over interned registers.
+//!
+//! Register names are replaced by dense indices into a `Vec<i64>`, and
+//! conditions are flattened into conditional jumps, so executing a program
+//! involves no hashing or recursion.
+
+use std::collections::HashMap;
+
+use super::{Change, Comparison, Condition, Instruction, Operand, Operation};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Arg {
+    Reg(usize),
+    Value(i64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    /// Jumps to `target` if the comparison evaluates to `when`.
+    Branch {
+        left: Arg,
+        comparison: Comparison,
+        right: Arg,
+        when: bool,
+        target: usize,
+    },
+    Jump(usize),
+    Apply {
+        reg: usize,
+        change: Change,
+        amount: i64,
+    },
+}
+
+/// A compiled program.
+pub struct Program<'s> {
+    names: Vec<&'s str>,
+    code: Vec<Op>,
+}
+
+struct Compiler<'s> {
+    indices: HashMap<&'s str, usize>,
+    names: Vec<&'s str>,
+    code: Vec<Op>,
+}
+
+impl<'s> Compiler<'s> {
+    fn intern(&mut self, name: &'s str) -> usize {
+        let names = &mut self.names;
+        *self.indices.entry(name).or_insert_with(|| {
+            names.push(name);
+            names.len() - 1
+        })
+    }
+
+    fn arg(&mut self, operand: &Operand<'s>) -> Arg {
+        match *operand {
+            Operand::Reg(name) => Arg::Reg(self.intern(name)),
+            Operand::Value(value) => Arg::Value(value as i64),
+        }
+    }
+
+    /// Emits a placeholder jump to be patched once its target is known.
+    fn emit_jump(&mut self) -> usize {
+        self.code.push(Op::Jump(usize::MAX));
+        self.code.len() - 1
+    }
+
+    fn patch(&mut self, at: usize, target: usize) {
+        match self.code[at] {
+            Op::Jump(ref mut t) | Op::Branch { target: ref mut t, .. } => *t = target,
+            Op::Apply { .. } => unreachable!(),
+        }
+    }
+
+    /// Emits code that jumps if `condition` evaluates to `when`, and falls
+    /// through otherwise. Returns the positions of the jumps to patch.
+    fn branch(&mut self, condition: &Condition<'s>, when: bool) -> Vec<usize> {
+        match *condition {
+            Condition::Compare(ref left, comparison, ref right) => {
+                let left = self.arg(left);
+                let right = self.arg(right);
+                self.code.push(Op::Branch {
+                    left,
+                    comparison,
+                    right,
+                    when,
+                    target: usize::MAX,
+                });
+                vec![self.code.len() - 1]
+            },
+            // `a and b` is false if either is false, and `a or b` is true if
+            // either is true.
+            Condition::And(ref a, ref b) if !when => self.branch_either(a, b, when),
+            Condition::Or(ref a, ref b) if when => self.branch_either(a, b, when),
+            Condition::And(ref a, ref b) | Condition::Or(ref a, ref b) =>
+                self.branch_both(a, b, when),
+        }
+    }
+
+    fn branch_either(&mut self, a: &Condition<'s>, b: &Condition<'s>, when: bool) -> Vec<usize> {
+        let mut jumps = self.branch(a, when);
+        jumps.extend(self.branch(b, when));
+        jumps
+    }
+
+    /// Jumps only if both `a` and `b` evaluate to `when`.
+    fn branch_both(&mut self, a: &Condition<'s>, b: &Condition<'s>, when: bool) -> Vec<usize> {
+        let skips = self.branch(a, !when);
+        let jumps = self.branch(b, when);
+        let next = self.code.len();
+        for skip in skips {
+            self.patch(skip, next);
+        }
+        jumps
+    }
+
+    fn apply(&mut self, operation: &Operation<'s>) {
+        let reg = self.intern(operation.reg);
+        self.code.push(Op::Apply {
+            reg,
+            change: operation.change,
+            amount: operation.amount as i64,
+        });
+    }
+
+    fn instruction(&mut self, instr: &Instruction<'s>) {
+        let to_else = self.branch(&instr.condition, false);
+        self.apply(&instr.operation);
+        let mut to_end = Vec::new();
+        if let Some(ref otherwise) = instr.otherwise {
+            to_end.push(self.emit_jump());
+            let else_start = self.code.len();
+            for at in to_else {
+                self.patch(at, else_start);
+            }
+            self.apply(otherwise);
+        } else {
+            to_end = to_else;
+        }
+        let end = self.code.len();
+        for at in to_end {
+            self.patch(at, end);
+        }
+    }
+}
+
+impl<'s> Program<'s> {
+    pub fn compile(instructions: &[Instruction<'s>]) -> Program<'s> {
+        let mut compiler = Compiler {
+            indices: HashMap::new(),
+            names: Vec::new(),
+            code: Vec::new(),
+        };
+        for instr in instructions.iter() {
+            compiler.instruction(instr);
+        }
+        Program {
+            names: compiler.names,
+            code: compiler.code,
+        }
+    }
+
+    /// The register names, in the order of their indices.
+    pub fn registers(&self) -> &[&'s str] {
+        &self.names
+    }
+
+    /// Runs the program with the given register values, which must have one
+    /// entry per register, and returns the highest value written.
+    pub fn exec(&self, registers: &mut [i64]) -> i64 {
+        assert_eq!(registers.len(), self.names.len());
+        let get = |registers: &[i64], arg: Arg| match arg {
+            Arg::Reg(reg) => registers[reg],
+            Arg::Value(value) => value,
+        };
+
+        let mut highest = 0;
+        let mut pc = 0;
+        while pc < self.code.len() {
+            match self.code[pc] {
+                Op::Branch { left, comparison, right, when, target } => {
+                    let left = get(registers, left);
+                    let right = get(registers, right);
+                    if comparison.test(left, right) == when {
+                        pc = target;
+                        continue;
+                    }
+                },
+                Op::Jump(target) => {
+                    pc = target;
+                    continue;
+                },
+                Op::Apply { reg, change, amount } => {
+                    change.apply(&mut registers[reg], amount);
+                    highest = highest.max(registers[reg]);
+                },
+            }
+            pc += 1;
+        }
+        highest
+    }
+
+    /// Runs the program from all-zero registers, returning the largest final
+    /// value and the highest value ever written, as `run` does.
+    pub fn run(&self) -> (i64, i64) {
+        let mut registers = vec![0; self.names.len()];
+        let highest = self.exec(&mut registers);
+        let largest = registers.iter().fold(0, |a, &b| a.max(b));
+        (largest, highest)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{parse_instructions, run};
+    use super::*;
+
+    #[test]
+    fn test_compound_conditions() {
+        let input = "a set 7 if x == 0\n\
+                     b set 3 if a > x and x == 0\n\
+                     a mul 6 if a < b or b == 3\n\
+                     a div 4 if a == 0 else b mod 2\n\
+                     c inc 1 if a == 42 and b == 0 or c != 0\n\
+                     d inc 2 if a != 42 or b == 1 and c == 0\n\
+                     e inc 3 if a < 1 and b > 0 or a > 1 and b < 0 else e dec 1";
+        let instructions = parse_instructions(input);
+        let (largest, highest) = run(&instructions);
+        let program = Program::compile(&instructions);
+        assert_eq!(program.run(), (largest as i64, highest as i64));
+
+        let mut registers = vec![0; program.registers().len()];
+        program.exec(&mut registers);
+        let value = |name| registers[program.registers().iter().position(|&r| r == name).unwrap()];
+        assert_eq!((value("a"), value("b"), value("c"), value("d"), value("e")), (42, 1, 0, 2, -1));
+    }
+}
@@ -0,0 +1,324 @@
+extern crate regex;
+
+pub mod analysis;
+pub mod bigint;
+mod bytecode;
+pub mod parser;
+pub mod trace;
+pub mod value;
+
+pub use bytecode::Program;
+pub use parser::{Diagnostic, Parser};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::{AddAssign, DivAssign, MulAssign, RemAssign, SubAssign};
+use value::Value;
+
+#[derive(Debug, Clone)]
+pub struct Instruction<'s> {
+    operation: Operation<'s>,
+    condition: Condition<'s>,
+    otherwise: Option<Operation<'s>>,
+}
+
+impl<'s> Instruction<'s> {
+    /// Executes the instruction on plain `isize` registers, which panics if
+    /// the result overflows; see `Change::apply`.
+    pub fn exec(&self, registers: &mut HashMap<&'s str, isize>) -> Outcome<'s> {
+        let outcome = self.exec_with(registers, |change, &value, amount| {
+            let mut value = value;
+            change.apply(&mut value, amount);
+            Some(value)
+        });
+        outcome.expect("unchecked changes always produce a value")
+    }
+
+    /// Like `exec`, but for any register type, and returns `None` and leaves
+    /// the registers unchanged if the result doesn't fit.
+    pub fn exec_checked<V: Value>(&self, registers: &mut HashMap<&'s str, V>) -> Option<Outcome<'s, V>> {
+        self.exec_with(registers, |change, value, amount| value.checked_apply(change, amount))
+    }
+
+    fn exec_with<V, F>(&self, registers: &mut HashMap<&'s str, V>, apply: F) -> Option<Outcome<'s, V>>
+        where V: Value, F: FnOnce(Change, &V, isize) -> Option<V>
+    {
+        let passed = self.condition.test(registers);
+        let operation = if passed {
+            &self.operation
+        } else {
+            match self.otherwise {
+                Some(ref operation) => operation,
+                None => return Some(Outcome { passed, change: None }),
+            }
+        };
+
+        let before = registers.get(operation.reg).cloned().unwrap_or_else(|| V::from_isize(0));
+        let after = apply(operation.change, &before, operation.amount)?;
+        registers.insert(operation.reg, after.clone());
+        Some(Outcome {
+            passed,
+            change: Some(RegisterChange {
+                reg: operation.reg,
+                before,
+                after,
+            }),
+        })
+    }
+}
+
+/// What happened when an instruction was executed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Outcome<'s, V = isize> {
+    /// Whether the instruction's condition held.
+    pub passed: bool,
+    /// The register written, if any. Instructions without an `else` branch
+    /// write nothing when their condition fails.
+    pub change: Option<RegisterChange<'s, V>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterChange<'s, V = isize> {
+    pub reg: &'s str,
+    pub before: V,
+    pub after: V,
+}
+
+/// Receives the outcome of every instruction executed by `run_with`.
+pub trait Observer<'s> {
+    fn observe(&mut self, index: usize, instr: &Instruction<'s>, outcome: &Outcome<'s>);
+}
+
+#[derive(Debug, Clone)]
+pub struct Operation<'s> {
+    reg: &'s str,
+    change: Change,
+    amount: isize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Change {
+    Inc,
+    Dec,
+    Set,
+    Mul,
+    Div,
+    Mod,
+}
+
+impl Change {
+    /// Applies the change with `T`'s own operators. For primitive integers
+    /// that means `mul`, `inc` and `dec` panic on overflow in debug builds,
+    /// and `div` or `mod` of the minimum value by -1 panics in any build.
+    /// Programs that might do either should be run through
+    /// `value::run_checked` (`--values i64` and friends) instead.
+    pub fn apply<T>(&self, value: &mut T, amount: T)
+        where T: Copy + AddAssign + SubAssign + MulAssign + DivAssign + RemAssign
+    {
+        match *self {
+            Change::Inc => { *value += amount; },
+            Change::Dec => { *value -= amount; },
+            Change::Set => { *value = amount; },
+            Change::Mul => { *value *= amount; },
+            Change::Div => { *value /= amount; },
+            Change::Mod => { *value %= amount; },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Condition<'s> {
+    Compare(Operand<'s>, Comparison, Operand<'s>),
+    And(Box<Condition<'s>>, Box<Condition<'s>>),
+    Or(Box<Condition<'s>>, Box<Condition<'s>>),
+}
+
+impl<'s> Condition<'s> {
+    fn test<V: Value>(&self, registers: &HashMap<&str, V>) -> bool {
+        match *self {
+            Condition::Compare(ref left, comparison, ref right) =>
+                comparison.test(left.value(registers), right.value(registers)),
+            Condition::And(ref a, ref b) => a.test(registers) && b.test(registers),
+            Condition::Or(ref a, ref b) => a.test(registers) || b.test(registers),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand<'s> {
+    Reg(&'s str),
+    Value(isize),
+}
+
+impl<'s> Operand<'s> {
+    fn value<V: Value>(&self, registers: &HashMap<&str, V>) -> V {
+        match *self {
+            Operand::Reg(reg) => registers.get(reg).cloned().unwrap_or_else(|| V::from_isize(0)),
+            Operand::Value(value) => V::from_isize(value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    pub fn test<T: Ord>(&self, left: T, right: T) -> bool {
+        match *self {
+            Comparison::Eq => left == right,
+            Comparison::Ne => left != right,
+            Comparison::Lt => left < right,
+            Comparison::Le => left <= right,
+            Comparison::Gt => left > right,
+            Comparison::Ge => left >= right,
+        }
+    }
+}
+
+impl<'s> fmt::Display for Instruction<'s> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} if {}", self.operation, self.condition)?;
+        if let Some(ref otherwise) = self.otherwise {
+            write!(f, " else {}", otherwise)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'s> fmt::Display for Operation<'s> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} {}", self.reg, self.change, self.amount)
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Change::Inc => "inc",
+            Change::Dec => "dec",
+            Change::Set => "set",
+            Change::Mul => "mul",
+            Change::Div => "div",
+            Change::Mod => "mod",
+        })
+    }
+}
+
+impl<'s> fmt::Display for Condition<'s> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Condition::Compare(ref left, comparison, ref right) =>
+                write!(f, "{} {} {}", left, comparison, right),
+            Condition::And(ref a, ref b) => write!(f, "{} and {}", a, b),
+            Condition::Or(ref a, ref b) => write!(f, "{} or {}", a, b),
+        }
+    }
+}
+
+impl<'s> fmt::Display for Operand<'s> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Operand::Reg(reg) => f.write_str(reg),
+            Operand::Value(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Comparison::Eq => "==",
+            Comparison::Ne => "!=",
+            Comparison::Lt => "<",
+            Comparison::Le => "<=",
+            Comparison::Gt => ">",
+            Comparison::Ge => ">=",
+        })
+    }
+}
+
+/// Parses a single instruction.
+pub fn parse_instruction<'s>(line: &'s str) -> Result<Instruction<'s>, Diagnostic> {
+    Parser::new().parse_line(line, 1)
+}
+
+/// Parses a program, panicking with every diagnostic if it is invalid.
+pub fn parse_instructions<'s>(input: &'s str) -> Vec<Instruction<'s>> {
+    match Parser::new().parse_program(input) {
+        Ok(instructions) => instructions,
+        Err(errors) => {
+            let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+            panic!("Invalid program:\n{}", messages.join("\n\n"));
+        },
+    }
+}
+
+/// Runs the instructions from empty registers, reporting each step to the
+/// observer, and returns the final registers. Like `Instruction::exec`, this
+/// panics if a register overflows.
+pub fn run_with<'s, O>(instructions: &[Instruction<'s>], observer: &mut O) -> HashMap<&'s str, isize>
+    where O: Observer<'s>
+{
+    let mut registers = HashMap::new();
+    for (index, instr) in instructions.iter().enumerate() {
+        let outcome = instr.exec(&mut registers);
+        observer.observe(index, instr, &outcome);
+    }
+    registers
+}
+
+/// Returns the largest final register value and the highest value ever
+/// written.
+pub fn run(instructions: &[Instruction]) -> (isize, isize) {
+    let mut highest = trace::Highest::new();
+    let registers = run_with(instructions, &mut highest);
+    let largest = registers.values().fold(0, |a, &b| std::cmp::max(a, b));
+    (largest, highest.value)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_example() {
+        let input = "b inc 5 if a > 1\n\
+                     a inc 1 if b < 5\n\
+                     c dec -10 if a >= 1\n\
+                     c inc -20 if c == 10";
+        let instructions = parse_instructions(input);
+        assert_eq!(run(&instructions), (1, 10));
+    }
+
+    #[test]
+    fn test_extended() {
+        let input = "a set 7 if x == 0\n\
+                     b set 3 if a > x and x == 0\n\
+                     a mul 6 if a < b or b == 3\n\
+                     a div 4 if a == 0 else b mod 2\n\
+                     c inc 1 if a == 42 and b == 0 or c != 0";
+        let instructions = parse_instructions(input);
+        let mut highest = trace::Highest::new();
+        let registers = run_with(&instructions, &mut highest);
+        assert_eq!(registers["a"], 42);
+        assert_eq!(registers["b"], 1);
+        assert_eq!(registers.get("c"), None);
+        assert_eq!(highest.value, 42);
+    }
+
+    #[test]
+    fn test_bytecode() {
+        let input = include_str!("input.txt");
+        let instructions = parse_instructions(input);
+        let (largest, highest) = run(&instructions);
+        let program = Program::compile(&instructions);
+        assert_eq!(program.run(), (largest as i64, highest as i64));
+    }
+}
@@ -0,0 +1,364 @@
+//! Parsing of register programs, with diagnostics that point at the
+//! offending token.
+
+use std::fmt;
+
+use regex::Regex;
+
+use super::{Change, Comparison, Condition, Instruction, Operand, Operation};
+
+/// What the parser was looking for when it found something else.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expected {
+    Register,
+    Change,
+    Integer,
+    /// A register or an integer.
+    Operand,
+    Comparison,
+    If,
+    /// `and`, `or`, `else` or the end of the line.
+    Connective,
+    End,
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Expected::Register => "register",
+            Expected::Change => "inc|dec|set|mul|div|mod",
+            Expected::Integer => "integer",
+            Expected::Operand => "register or integer",
+            Expected::Comparison => "comparison operator (==, !=, <, <=, >, >=)",
+            Expected::If => "`if`",
+            Expected::Connective => "`and`, `or`, `else` or end of line",
+            Expected::End => "end of line",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// An unexpected token, or the end of the line if `found` is `None`.
+    Unexpected {
+        expected: Expected,
+        found: Option<String>,
+    },
+    NumberOutOfRange,
+    DivisionByZero,
+}
+
+/// A parse error at a particular place in the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The 1-based line number.
+    pub line: usize,
+    /// The 1-based column, in characters.
+    pub column: usize,
+    /// The number of characters the error spans, at least 1.
+    pub width: usize,
+    pub kind: ErrorKind,
+    /// The full text of the offending line.
+    pub source: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+        match self.kind {
+            ErrorKind::Unexpected { expected, found: Some(ref found) } =>
+                writeln!(f, "expected {}, found `{}`", expected, found)?,
+            ErrorKind::Unexpected { expected, found: None } =>
+                writeln!(f, "expected {}, found end of line", expected)?,
+            ErrorKind::NumberOutOfRange => writeln!(f, "number out of range")?,
+            ErrorKind::DivisionByZero => writeln!(f, "division by zero")?,
+        }
+
+        let number = self.line.to_string();
+        writeln!(f, "{} | {}", number, self.source)?;
+        write!(f, "{:w$} | {:c$}{}", "", "", "^".repeat(self.width),
+            w = number.len(), c = self.column - 1)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Token<'s> {
+    text: &'s str,
+    /// The byte offset within the line.
+    start: usize,
+}
+
+struct LineParser<'s> {
+    line: &'s str,
+    number: usize,
+    tokens: Vec<Token<'s>>,
+    pos: usize,
+}
+
+impl<'s> LineParser<'s> {
+    fn error(&self, token: Option<Token<'s>>, kind: ErrorKind) -> Diagnostic {
+        // Point just past the last token if the line ended early
+        let (start, width) = match token {
+            Some(token) => (token.start, token.text.chars().count()),
+            None => (self.line.trim_end().len(), 1),
+        };
+        Diagnostic {
+            line: self.number,
+            column: self.line[..start].chars().count() + 1,
+            width,
+            kind,
+            source: self.line.to_string(),
+        }
+    }
+
+    fn unexpected(&self, expected: Expected) -> Diagnostic {
+        let token = self.tokens.get(self.pos).cloned();
+        let found = token.map(|t| t.text.to_string());
+        self.error(token, ErrorKind::Unexpected { expected, found })
+    }
+
+    fn peek(&self) -> Option<&'s str> {
+        self.tokens.get(self.pos).map(|t| t.text)
+    }
+
+    fn next(&mut self) -> Option<Token<'s>> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn keyword(&mut self, keyword: &str, expected: Expected) -> Result<(), Diagnostic> {
+        if self.peek() == Some(keyword) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.unexpected(expected))
+        }
+    }
+
+    fn register(&mut self) -> Result<&'s str, Diagnostic> {
+        match self.peek() {
+            Some(text) if is_register(text) => {
+                self.pos += 1;
+                Ok(text)
+            },
+            _ => Err(self.unexpected(Expected::Register)),
+        }
+    }
+
+    fn integer(&mut self, expected: Expected) -> Result<isize, Diagnostic> {
+        match self.peek() {
+            Some(text) if is_integer(text) => {
+                let token = self.next();
+                text.parse().map_err(|_| self.error(token, ErrorKind::NumberOutOfRange))
+            },
+            _ => Err(self.unexpected(expected)),
+        }
+    }
+
+    fn operation(&mut self) -> Result<Operation<'s>, Diagnostic> {
+        let reg = self.register()?;
+        let change = match self.peek() {
+            Some("inc") => Change::Inc,
+            Some("dec") => Change::Dec,
+            Some("set") => Change::Set,
+            Some("mul") => Change::Mul,
+            Some("div") => Change::Div,
+            Some("mod") => Change::Mod,
+            _ => return Err(self.unexpected(Expected::Change)),
+        };
+        self.pos += 1;
+        let amount_token = self.tokens.get(self.pos).cloned();
+        let amount = self.integer(Expected::Integer)?;
+        if amount == 0 && (change == Change::Div || change == Change::Mod) {
+            return Err(self.error(amount_token, ErrorKind::DivisionByZero));
+        }
+
+        Ok(Operation {
+            reg,
+            change,
+            amount,
+        })
+    }
+
+    fn operand(&mut self) -> Result<Operand<'s>, Diagnostic> {
+        match self.peek() {
+            Some(text) if is_register(text) => {
+                self.pos += 1;
+                Ok(Operand::Reg(text))
+            },
+            _ => self.integer(Expected::Operand).map(Operand::Value),
+        }
+    }
+
+    fn comparison(&mut self) -> Result<Condition<'s>, Diagnostic> {
+        let left = self.operand()?;
+        let comparison = match self.peek() {
+            Some("==") => Comparison::Eq,
+            Some("!=") => Comparison::Ne,
+            Some("<") => Comparison::Lt,
+            Some("<=") => Comparison::Le,
+            Some(">") => Comparison::Gt,
+            Some(">=") => Comparison::Ge,
+            _ => return Err(self.unexpected(Expected::Comparison)),
+        };
+        self.pos += 1;
+        let right = self.operand()?;
+        Ok(Condition::Compare(left, comparison, right))
+    }
+
+    /// Parses comparisons joined by `and` and `or`, where `and` binds tighter.
+    fn condition(&mut self) -> Result<Condition<'s>, Diagnostic> {
+        let mut any = None;
+        loop {
+            let mut all = self.comparison()?;
+            while self.peek() == Some("and") {
+                self.pos += 1;
+                all = Condition::And(Box::new(all), Box::new(self.comparison()?));
+            }
+            any = Some(match any {
+                Some(acc) => Condition::Or(Box::new(acc), Box::new(all)),
+                None => all,
+            });
+            if self.peek() != Some("or") {
+                return Ok(any.unwrap());
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn instruction(&mut self) -> Result<Instruction<'s>, Diagnostic> {
+        let operation = self.operation()?;
+        self.keyword("if", Expected::If)?;
+        let condition = self.condition()?;
+        let otherwise = match self.peek() {
+            Some("else") => {
+                self.pos += 1;
+                Some(self.operation()?)
+            },
+            None => None,
+            Some(_) => return Err(self.unexpected(Expected::Connective)),
+        };
+        if self.peek().is_some() {
+            return Err(self.unexpected(Expected::End));
+        }
+
+        Ok(Instruction {
+            operation,
+            condition,
+            otherwise,
+        })
+    }
+}
+
+fn is_register(text: &str) -> bool {
+    text.starts_with(|c: char| c.is_ascii_lowercase())
+}
+
+fn is_integer(text: &str) -> bool {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())
+}
+
+pub struct Parser {
+    token: Regex,
+}
+
+impl Parser {
+    pub fn new() -> Parser {
+        Parser {
+            token: Regex::new(r"[a-z]+|-?[0-9]+|[=!<>]=?|\S").unwrap(),
+        }
+    }
+
+    /// Parses a single line, which may not be blank, numbering it `number` in
+    /// diagnostics. Anything after a `#` is ignored.
+    pub fn parse_line<'s>(&self, line: &'s str, number: usize) -> Result<Instruction<'s>, Diagnostic> {
+        let code = line.split('#').next().unwrap();
+        let tokens = self.token.find_iter(code)
+            .map(|m| Token { text: m.as_str(), start: m.start() })
+            .collect();
+        let mut parser = LineParser {
+            line,
+            number,
+            tokens,
+            pos: 0,
+        };
+        parser.instruction()
+    }
+
+    /// Parses a whole program, skipping blank and comment-only lines, and
+    /// returns either every instruction or every error.
+    pub fn parse_program<'s>(&self, input: &'s str) -> Result<Vec<Instruction<'s>>, Vec<Diagnostic>> {
+        let lines = self.parse_program_lines(input)?;
+        Ok(lines.into_iter().map(|(_, instr)| instr).collect())
+    }
+
+    /// Like `parse_program`, but pairs each instruction with its 1-based
+    /// line number, since blank and comment lines are skipped.
+    pub fn parse_program_lines<'s>(&self, input: &'s str)
+        -> Result<Vec<(usize, Instruction<'s>)>, Vec<Diagnostic>>
+    {
+        let mut instructions = Vec::new();
+        let mut errors = Vec::new();
+        for (index, line) in input.lines().enumerate() {
+            if line.split('#').next().unwrap().trim().is_empty() {
+                continue;
+            }
+            match self.parse_line(line, index + 1) {
+                Ok(instr) => instructions.push((index + 1, instr)),
+                Err(e) => errors.push(e),
+            }
+        }
+        if errors.is_empty() {
+            Ok(instructions)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl Default for Parser {
+    fn default() -> Parser {
+        Parser::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_diagnostics() {
+        let input = "# A comment\n\
+                     a inc 5 if b < 1\n\
+                     \n\
+                     a inc x if b < 1\n\
+                     b dce 2 if a > 1  # typo\n\
+                     c mod 0 if a > 1\n\
+                     d set 1 if a = 1\n\
+                     e inc 1 if a > 1 else\n\
+                     f inc 1 if a > 99999999999999999999";
+        let errors = Parser::new().parse_program(input).unwrap_err();
+        let summary: Vec<_> = errors.iter()
+            .map(|e| (e.line, e.column, e.kind.clone()))
+            .collect();
+        let unexpected = |expected, found: Option<&str>| ErrorKind::Unexpected {
+            expected,
+            found: found.map(|s| s.to_string()),
+        };
+        assert_eq!(summary, vec![
+            (4, 7, unexpected(Expected::Integer, Some("x"))),
+            (5, 3, unexpected(Expected::Change, Some("dce"))),
+            (6, 7, ErrorKind::DivisionByZero),
+            (7, 14, unexpected(Expected::Comparison, Some("="))),
+            (8, 22, unexpected(Expected::Register, None)),
+            (9, 16, ErrorKind::NumberOutOfRange),
+        ]);
+
+        assert_eq!(errors[1].to_string(), "\
+line 5, column 3: expected inc|dec|set|mul|div|mod, found `dce`
+5 | b dce 2 if a > 1  # typo
+  |   ^^^");
+    }
+}
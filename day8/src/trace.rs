@@ -0,0 +1,174 @@
+//! Observers for `run_with`.
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use super::{Instruction, Observer, Outcome};
+
+impl<'s, A, B> Observer<'s> for (A, B) where A: Observer<'s>, B: Observer<'s> {
+    fn observe(&mut self, index: usize, instr: &Instruction<'s>, outcome: &Outcome<'s>) {
+        self.0.observe(index, instr, outcome);
+        self.1.observe(index, instr, outcome);
+    }
+}
+
+/// Tracks the highest value ever written to any register.
+pub struct Highest {
+    pub value: isize,
+}
+
+impl Highest {
+    pub fn new() -> Highest {
+        // Registers start at 0, so that's the highest value before any writes
+        Highest { value: 0 }
+    }
+}
+
+impl Default for Highest {
+    fn default() -> Highest {
+        Highest::new()
+    }
+}
+
+impl<'s> Observer<'s> for Highest {
+    fn observe(&mut self, _: usize, _: &Instruction<'s>, outcome: &Outcome<'s>) {
+        if let Some(change) = outcome.change {
+            self.value = std::cmp::max(self.value, change.after);
+        }
+    }
+}
+
+/// The range of values a register has held.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Range {
+    pub min: isize,
+    pub max: isize,
+    pub writes: usize,
+}
+
+/// Tracks the minimum and maximum value of each register that is written.
+#[derive(Default)]
+pub struct RegisterRanges<'s> {
+    pub ranges: HashMap<&'s str, Range>,
+}
+
+impl<'s> Observer<'s> for RegisterRanges<'s> {
+    fn observe(&mut self, _: usize, _: &Instruction<'s>, outcome: &Outcome<'s>) {
+        if let Some(change) = outcome.change {
+            // Every register starts out as 0 before its first write
+            let range = self.ranges.entry(change.reg).or_insert(Range {
+                min: change.before,
+                max: change.before,
+                writes: 0,
+            });
+            range.min = std::cmp::min(range.min, change.after);
+            range.max = std::cmp::max(range.max, change.after);
+            range.writes += 1;
+        }
+    }
+}
+
+/// Counts how often each instruction's condition held and how often it
+/// wrote to a register.
+#[derive(Default)]
+pub struct FireCounts {
+    pub passed: Vec<usize>,
+    pub fired: Vec<usize>,
+}
+
+impl FireCounts {
+    /// The indices of instructions that never wrote to a register.
+    pub fn never_fired(&self) -> Vec<usize> {
+        self.fired.iter()
+            .enumerate()
+            .filter(|&(_, &count)| count == 0)
+            .map(|(index, _)| index)
+            .collect()
+    }
+}
+
+impl<'s> Observer<'s> for FireCounts {
+    fn observe(&mut self, index: usize, _: &Instruction<'s>, outcome: &Outcome<'s>) {
+        if self.passed.len() <= index {
+            self.passed.resize(index + 1, 0);
+            self.fired.resize(index + 1, 0);
+        }
+        if outcome.passed {
+            self.passed[index] += 1;
+        }
+        if outcome.change.is_some() {
+            self.fired[index] += 1;
+        }
+    }
+}
+
+/// Records every step so it can be written out afterwards.
+#[derive(Default)]
+pub struct Trace<'s> {
+    pub steps: Vec<(usize, String, Outcome<'s>)>,
+}
+
+impl<'s> Trace<'s> {
+    pub fn write_text<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        for &(index, ref instr, outcome) in self.steps.iter() {
+            write!(out, "{:5}  {}  => {}", index, instr, outcome.passed)?;
+            if let Some(change) = outcome.change {
+                write!(out, ", {}: {} -> {}", change.reg, change.before, change.after)?;
+            }
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the trace as a JSON array with one object per step.
+    pub fn write_json<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "[")?;
+        for (i, &(index, ref instr, outcome)) in self.steps.iter().enumerate() {
+            // Instructions only contain register names, numbers and
+            // operators, none of which need escaping.
+            write!(out, "  {{\"index\": {}, \"instruction\": \"{}\", \"passed\": {}",
+                index, instr, outcome.passed)?;
+            if let Some(change) = outcome.change {
+                write!(out, ", \"change\": {{\"register\": \"{}\", \"before\": {}, \"after\": {}}}",
+                    change.reg, change.before, change.after)?;
+            }
+            let separator = if i + 1 < self.steps.len() { "," } else { "" };
+            writeln!(out, "}}{}", separator)?;
+        }
+        writeln!(out, "]")
+    }
+}
+
+impl<'s> Observer<'s> for Trace<'s> {
+    fn observe(&mut self, index: usize, instr: &Instruction<'s>, outcome: &Outcome<'s>) {
+        self.steps.push((index, instr.to_string(), *outcome));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::{parse_instructions, run_with};
+
+    #[test]
+    fn test_observers() {
+        let input = "b inc 5 if a > 1\n\
+                     a inc 1 if b < 5\n\
+                     c dec -10 if a >= 1\n\
+                     c inc -20 if c == 10";
+        let instructions = parse_instructions(input);
+        let mut observers = (RegisterRanges::default(), (FireCounts::default(), Trace::default()));
+        run_with(&instructions, &mut observers);
+        let (ranges, (counts, trace)) = observers;
+
+        assert_eq!(ranges.ranges["c"], Range { min: -10, max: 10, writes: 2 });
+        assert_eq!(ranges.ranges.get("b"), None);
+        assert_eq!(counts.never_fired(), vec![0]);
+
+        let mut text = Vec::new();
+        trace.write_text(&mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert_eq!(text.lines().nth(3).unwrap(),
+            "    3  c inc -20 if c == 10  => true, c: 10 -> -10");
+    }
+}
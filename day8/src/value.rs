@@ -0,0 +1,135 @@
+//! Register value types, and a checked interpreter that is generic over them.
+
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Display};
+
+use super::{Change, Instruction};
+use bigint::BigInt;
+
+/// A type that registers can hold.
+pub trait Value: Clone + Ord + Debug + Display {
+    fn from_isize(n: isize) -> Self;
+
+    /// Applies a change, or returns `None` if the result doesn't fit.
+    fn checked_apply(&self, change: Change, amount: isize) -> Option<Self>;
+}
+
+macro_rules! impl_value {
+    ($($t:ty),*) => {$(
+        impl Value for $t {
+            fn from_isize(n: isize) -> $t {
+                n as $t
+            }
+
+            fn checked_apply(&self, change: Change, amount: isize) -> Option<$t> {
+                let amount = amount as $t;
+                match change {
+                    Change::Inc => self.checked_add(amount),
+                    Change::Dec => self.checked_sub(amount),
+                    Change::Set => Some(amount),
+                    Change::Mul => self.checked_mul(amount),
+                    Change::Div => self.checked_div(amount),
+                    Change::Mod => self.checked_rem(amount),
+                }
+            }
+        }
+    )*}
+}
+
+// Every isize fits in these types, so literals convert losslessly.
+impl_value!(isize, i64, i128);
+
+impl Value for BigInt {
+    fn from_isize(n: isize) -> BigInt {
+        BigInt::from_i128(n as i128)
+    }
+
+    fn checked_apply(&self, change: Change, amount: isize) -> Option<BigInt> {
+        Some(match change {
+            Change::Inc => self.add(&BigInt::from_isize(amount)),
+            Change::Dec => self.sub(&BigInt::from_isize(amount)),
+            Change::Set => BigInt::from_isize(amount),
+            Change::Mul => self.mul_i64(amount as i64),
+            Change::Div => self.divrem_i64(amount as i64).0,
+            Change::Mod => self.divrem_i64(amount as i64).1,
+        })
+    }
+}
+
+/// An instruction that produced a value its register couldn't hold.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Overflow {
+    pub index: usize,
+    pub instruction: String,
+}
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "overflow in instruction {}: {}", self.index, self.instruction)
+    }
+}
+
+/// Runs the instructions with registers of type `V`, returning the largest
+/// final value and the highest value ever written, or the first instruction
+/// that overflowed.
+pub fn run_checked<V: Value>(instructions: &[Instruction]) -> Result<(V, V), Overflow> {
+    let zero = V::from_isize(0);
+    let mut highest = zero.clone();
+    let mut registers: HashMap<&str, V> = HashMap::new();
+    for (index, instr) in instructions.iter().enumerate() {
+        let outcome = instr.exec_checked(&mut registers).ok_or_else(|| Overflow {
+            index,
+            instruction: instr.to_string(),
+        })?;
+        if let Some(change) = outcome.change {
+            if change.after > highest {
+                highest = change.after;
+            }
+        }
+    }
+
+    let largest = registers.values().fold(zero, |a, b| std::cmp::max(a, b.clone()));
+    Ok((largest, highest))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::{parse_instructions, run};
+
+    #[test]
+    fn test_value_types() {
+        let instructions = parse_instructions(include_str!("input.txt"));
+        let (largest, highest) = run(&instructions);
+        assert_eq!(run_checked::<i64>(&instructions), Ok((largest as i64, highest as i64)));
+        assert_eq!(run_checked::<i128>(&instructions), Ok((largest as i128, highest as i128)));
+        assert_eq!(run_checked::<BigInt>(&instructions),
+            Ok((BigInt::from_isize(largest), BigInt::from_isize(highest))));
+    }
+
+    #[test]
+    fn test_overflow() {
+        let input = "a set 1 if a == 0\n\
+                     a mul 4294967296 if a > 0\n\
+                     a mul 4294967296 if a > 0\n\
+                     a div 2 if a > 0";
+        let instructions = parse_instructions(input);
+        assert_eq!(run_checked::<i64>(&instructions).unwrap_err().index, 2);
+        let (largest, _) = run_checked::<i128>(&instructions).unwrap();
+        assert_eq!(largest, 1 << 63);
+        let (largest, _) = run_checked::<BigInt>(&instructions).unwrap();
+        assert_eq!(largest.to_string(), "9223372036854775808");
+    }
+
+    #[test]
+    fn test_min_div() {
+        // Would panic in `run` even in a release build
+        let input = "a dec 9223372036854775807 if a == 0\n\
+                     a dec 1 if a < 0\n\
+                     a div -1 if a < 0";
+        let instructions = parse_instructions(input);
+        assert_eq!(run_checked::<i64>(&instructions).unwrap_err().index, 2);
+        let (largest, _) = run_checked::<i128>(&instructions).unwrap();
+        assert_eq!(largest, 1 << 63);
+    }
+}
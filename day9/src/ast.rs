@@ -0,0 +1,292 @@
+//! A tree representation of a stream.
+
+use std::fmt;
+use std::ops::Range;
+
+use super::{visit_data, ErrorKind, StreamError, StreamVisitor};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+    Group {
+        /// The bytes of the source from the `{` to the `}`, inclusive.
+        span: Range<usize>,
+        children: Vec<Node>,
+    },
+    Garbage {
+        /// The bytes of the source from the `<` to the `>`, inclusive.
+        span: Range<usize>,
+        /// The garbage with cancelled characters removed.
+        content: String,
+    },
+}
+
+impl Node {
+    pub fn span(&self) -> Range<usize> {
+        match *self {
+            Node::Group { ref span, .. } | Node::Garbage { ref span, .. } => span.clone(),
+        }
+    }
+
+    fn children(&self) -> &[Node] {
+        match *self {
+            Node::Group { ref children, .. } => children,
+            Node::Garbage { .. } => &[],
+        }
+    }
+
+    /// Calls `f` with every node and its depth, where the depth of a group
+    /// counts itself and the depth of garbage is that of its group.
+    fn walk<'a, F: FnMut(&'a Node, usize)>(&'a self, depth: usize, f: &mut F) {
+        let depth = match *self {
+            Node::Group { .. } => depth + 1,
+            Node::Garbage { .. } => depth,
+        };
+        f(self, depth);
+        for child in self.children() {
+            child.walk(depth, f);
+        }
+    }
+
+    /// Returns the first of the most deeply nested groups, with its depth.
+    pub fn deepest_group(&self) -> Option<(usize, &Node)> {
+        let mut deepest: Option<(usize, &Node)> = None;
+        self.walk(0, &mut |node, depth| {
+            if let Node::Group { .. } = *node {
+                if deepest.is_none_or(|(d, _)| depth > d) {
+                    deepest = Some((depth, node));
+                }
+            }
+        });
+        deepest
+    }
+
+    /// Counts the groups at each depth, starting with depth 1.
+    pub fn groups_per_depth(&self) -> Vec<usize> {
+        let mut counts = Vec::new();
+        self.walk(0, &mut |node, depth| {
+            if let Node::Group { .. } = *node {
+                if counts.len() < depth {
+                    counts.resize(depth, 0);
+                }
+                counts[depth - 1] += 1;
+            }
+        });
+        counts
+    }
+
+    /// Returns the first of the garbage blocks with the most content.
+    pub fn largest_garbage(&self) -> Option<&Node> {
+        let mut largest: Option<(usize, &Node)> = None;
+        self.walk(0, &mut |node, _| {
+            if let Node::Garbage { ref content, .. } = *node {
+                let length = content.chars().count();
+                if largest.is_none_or(|(l, _)| length > l) {
+                    largest = Some((length, node));
+                }
+            }
+        });
+        largest.map(|(_, node)| node)
+    }
+
+    /// The total score of all groups.
+    pub fn score(&self) -> usize {
+        let mut score = 0;
+        self.walk(0, &mut |node, depth| {
+            if let Node::Group { .. } = *node {
+                score += depth;
+            }
+        });
+        score
+    }
+
+    /// The number of non-cancelled characters within garbage.
+    pub fn garbage_count(&self) -> usize {
+        let mut count = 0;
+        self.walk(0, &mut |node, _| {
+            if let Node::Garbage { ref content, .. } = *node {
+                count += content.chars().count();
+            }
+        });
+        count
+    }
+}
+
+impl Node {
+    fn write_garbage(content: &str, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("<")?;
+        for ch in content.chars() {
+            if ch == '!' || ch == '>' {
+                f.write_str("!")?;
+            }
+            write!(f, "{}", ch)?;
+        }
+        f.write_str(">")
+    }
+
+    fn write_indented(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        match *self {
+            Node::Group { ref children, .. } if children.is_empty() => f.write_str("{}"),
+            Node::Group { ref children, .. } => {
+                f.write_str("{\n")?;
+                for (i, child) in children.iter().enumerate() {
+                    write!(f, "{:1$}", "", indent + 2)?;
+                    child.write_indented(f, indent + 2)?;
+                    f.write_str(if i + 1 < children.len() { ",\n" } else { "\n" })?;
+                }
+                write!(f, "{:1$}}}", "", indent)
+            },
+            Node::Garbage { ref content, .. } => Node::write_garbage(content, f),
+        }
+    }
+}
+
+/// Writes the node as stream syntax, cancelling any `!` or `>` in garbage.
+///
+/// The alternate form (`{:#}`) puts each child on its own indented line.
+/// That's easier to read, but isn't valid stream syntax since the stream
+/// doesn't allow whitespace outside garbage.
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            return self.write_indented(f, 0);
+        }
+        match *self {
+            Node::Group { ref children, .. } => {
+                f.write_str("{")?;
+                for (i, child) in children.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}", child)?;
+                }
+                f.write_str("}")
+            },
+            Node::Garbage { ref content, .. } => Node::write_garbage(content, f),
+        }
+    }
+}
+
+#[derive(Default)]
+struct TreeBuilder {
+    /// The start offsets and children of the currently open groups.
+    open: Vec<(usize, Vec<Node>)>,
+    garbage_start: usize,
+    root: Option<Node>,
+    /// The start of the first top-level node after the root, if any.
+    extra_root: Option<usize>,
+}
+
+impl TreeBuilder {
+    fn add(&mut self, node: Node) {
+        match self.open.last_mut() {
+            Some(&mut (_, ref mut children)) => children.push(node),
+            None if self.root.is_some() => {
+                if self.extra_root.is_none() {
+                    self.extra_root = Some(node.span().start);
+                }
+            },
+            None => self.root = Some(node),
+        }
+    }
+}
+
+impl StreamVisitor for TreeBuilder {
+    fn begin_group(&mut self, _depth: usize, offset: usize) {
+        self.open.push((offset, Vec::new()));
+    }
+
+    fn end_group(&mut self, _depth: usize, offset: usize) {
+        let (start, children) = self.open.pop().unwrap();
+        self.add(Node::Group {
+            span: start..offset + 1,
+            children,
+        });
+    }
+
+    fn begin_garbage(&mut self, offset: usize) {
+        self.garbage_start = offset;
+    }
+
+    fn end_garbage(&mut self, offset: usize, _raw: &str, content: &str) {
+        let node = Node::Garbage {
+            span: self.garbage_start..offset + 1,
+            content: content.to_string(),
+        };
+        self.add(node);
+    }
+}
+
+/// Builds the tree for a stream, which must consist of a single group or
+/// garbage block.
+pub fn parse_tree(input: &str) -> Result<Node, StreamError> {
+    let mut builder = TreeBuilder::default();
+    visit_data(input, &mut builder)?;
+    if let Some(offset) = builder.extra_root {
+        return Err(StreamError {
+            offset,
+            kind: ErrorKind::MultipleRoots,
+        });
+    }
+    builder.root.ok_or(StreamError {
+        offset: 0,
+        kind: ErrorKind::Empty,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_queries() {
+        let input = "{{<a!>b>},{{<>}},{<xyzw>}}";
+        let tree = parse_tree(input).unwrap();
+        assert_eq!(tree.span(), 0..input.len());
+        assert_eq!(tree.groups_per_depth(), vec![1, 3, 1]);
+
+        let (depth, deepest) = tree.deepest_group().unwrap();
+        assert_eq!((depth, &input[deepest.span()]), (3, "{<>}"));
+
+        let largest = tree.largest_garbage().unwrap();
+        assert_eq!(&input[largest.span()], "<xyzw>");
+    }
+
+    #[test]
+    fn test_multiple_roots() {
+        let error = parse_tree("{},<a>,{}").unwrap_err();
+        assert_eq!((error.offset, error.kind), (3, ErrorKind::MultipleRoots));
+        assert!(parse_tree("{{},{}}").is_ok());
+    }
+
+    #[test]
+    fn test_indented() {
+        let tree = parse_tree("{{<a!>b>},{},{{<>}}}").unwrap();
+        assert_eq!(format!("{:#}", tree), "\
+{
+  {
+    <ab>
+  },
+  {},
+  {
+    {
+      <>
+    }
+  }
+}");
+        assert_eq!(format!("{:#}", parse_tree("<x>").unwrap()), "<x>");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let input = include_str!("input.txt");
+        let tree = parse_tree(input).unwrap();
+        let mut counter = ::Counter::new();
+        visit_data(input, &mut counter).unwrap();
+        assert_eq!((tree.score(), tree.garbage_count()), (counter.score, counter.garbage));
+
+        let serialized = tree.to_string();
+        let reparsed = parse_tree(&serialized).unwrap();
+        assert_eq!(reparsed.to_string(), serialized);
+        assert_eq!((reparsed.score(), reparsed.garbage_count()), (counter.score, counter.garbage));
+    }
+}
@@ -0,0 +1,210 @@
+//! Random generation of valid streams with known answers, for stress
+//! testing the scanner.
+
+/// A small xorshift64* generator, so that streams can be reproduced from a
+/// seed without any dependencies.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // The state must never be zero
+        Rng(seed ^ 0x9e37_79b9_7f4a_7c15 | 1)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Returns a number in `0..n`.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// Returns true with probability `p`.
+    pub fn chance(&mut self, p: f64) -> bool {
+        let unit = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        unit < p
+    }
+}
+
+pub struct Config {
+    /// The maximum nesting depth of groups, at least 1.
+    pub max_depth: usize,
+    /// The maximum number of items in a group.
+    pub max_children: usize,
+    /// The probability that an item in a group is garbage rather than a
+    /// nested group.
+    pub garbage_ratio: f64,
+    /// The maximum number of characters in a garbage block, including
+    /// cancelled ones.
+    pub max_garbage: usize,
+    /// The probability that a garbage character is a cancelled one.
+    pub escape_density: f64,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            max_depth: 8,
+            max_children: 4,
+            garbage_ratio: 0.4,
+            max_garbage: 12,
+            escape_density: 0.2,
+        }
+    }
+}
+
+/// A generated stream with its expected answers.
+pub struct Generated {
+    pub text: String,
+    pub score: usize,
+    pub garbage: usize,
+}
+
+/// Characters that may appear uncancelled in garbage.
+const GARBAGE_CHARS: &[u8] = b"{}<,'\"aeiou";
+/// Characters that may be cancelled in garbage, including the two that must
+/// be.
+const CANCELLED_CHARS: &[u8] = b"!>{}<,'\"aeiou";
+
+struct Generator<'a> {
+    config: &'a Config,
+    rng: &'a mut Rng,
+    out: Generated,
+}
+
+impl<'a> Generator<'a> {
+    fn group(&mut self, depth: usize) {
+        self.out.text.push('{');
+        self.out.score += depth;
+        let children = if depth < self.config.max_depth {
+            self.rng.below(self.config.max_children + 1)
+        } else {
+            0
+        };
+        for i in 0..children {
+            if i > 0 {
+                self.out.text.push(',');
+            }
+            if depth + 1 > self.config.max_depth || self.rng.chance(self.config.garbage_ratio) {
+                self.garbage();
+            } else {
+                self.group(depth + 1);
+            }
+        }
+        self.out.text.push('}');
+    }
+
+    fn garbage(&mut self) {
+        self.out.text.push('<');
+        for _ in 0..self.rng.below(self.config.max_garbage + 1) {
+            if self.rng.chance(self.config.escape_density) {
+                self.out.text.push('!');
+                self.out.text.push(CANCELLED_CHARS[self.rng.below(CANCELLED_CHARS.len())] as char);
+            } else {
+                self.out.text.push(GARBAGE_CHARS[self.rng.below(GARBAGE_CHARS.len())] as char);
+                self.out.garbage += 1;
+            }
+        }
+        self.out.text.push('>');
+    }
+}
+
+/// Generates a random stream consisting of a single outer group.
+pub fn generate(config: &Config, rng: &mut Rng) -> Generated {
+    let mut generator = Generator {
+        config,
+        rng,
+        out: Generated {
+            text: String::new(),
+            score: 0,
+            garbage: 0,
+        },
+    };
+    generator.group(1);
+    generator.out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ast::parse_tree;
+    use stream::StreamParser;
+    use {visit_data, visit_data_recovering, Counter, StreamError};
+
+    #[test]
+    fn test_generated_streams() {
+        let mut rng = Rng::new(2017);
+        for i in 0..500 {
+            let config = Config {
+                max_depth: 1 + i % 10,
+                escape_density: (i % 5) as f64 / 4.0,
+                ..Config::default()
+            };
+            let stream = generate(&config, &mut rng);
+
+            let mut counter = Counter::new();
+            visit_data(&stream.text, &mut counter).unwrap();
+            assert_eq!((counter.score, counter.garbage), (stream.score, stream.garbage),
+                "{}", stream.text);
+
+            let tree = parse_tree(&stream.text).unwrap();
+            assert_eq!((tree.score(), tree.garbage_count()), (stream.score, stream.garbage));
+
+            // Feed the stream in random chunks
+            let bytes = stream.text.as_bytes();
+            let mut counter = Counter::new();
+            let mut parser = StreamParser::new(false);
+            let mut start = 0;
+            while start < bytes.len() {
+                let end = (start + 1 + rng.below(16)).min(bytes.len());
+                parser.feed(&bytes[start..end], &mut counter);
+                start = end;
+            }
+            assert!(parser.finish(&mut counter).is_empty());
+            assert_eq!((counter.score, counter.garbage), (stream.score, stream.garbage));
+        }
+    }
+
+    #[test]
+    fn test_arbitrary_bytes() {
+        // Mostly structural characters, so that the interesting states are
+        // reached, with the occasional arbitrary byte.
+        let alphabet = b"{}<>!,a";
+        let mut rng = Rng::new(9);
+        for _ in 0..2000 {
+            let bytes: Vec<u8> = (0..rng.below(64))
+                .map(|_| if rng.chance(0.05) {
+                    rng.next_u64() as u8
+                } else {
+                    alphabet[rng.below(alphabet.len())]
+                })
+                .collect();
+
+            // Strict mode stops exactly where recovery reports its first error
+            let mut strict = Counter::new();
+            let mut parser = StreamParser::new(false);
+            parser.feed(&bytes, &mut strict);
+            let strict_errors = parser.finish(&mut strict);
+
+            let mut recovering = Counter::new();
+            let mut parser = StreamParser::new(true);
+            parser.feed(&bytes, &mut recovering);
+            let errors = parser.finish(&mut recovering);
+            assert_eq!(strict_errors.first(), errors.first());
+            assert!(strict_errors.len() <= 1);
+
+            // Invalid UTF-8 is replaced the same way, but the replacement
+            // character is longer so offsets can differ
+            let text = String::from_utf8_lossy(&bytes);
+            let kinds = |errors: &[StreamError]| errors.iter().map(|e| e.kind).collect::<Vec<_>>();
+            let first = visit_data(&text, &mut Counter::new()).err();
+            assert_eq!(first.map(|e| e.kind), errors.first().map(|e| e.kind));
+            assert_eq!(kinds(&visit_data_recovering(&text, &mut Counter::new())), kinds(&errors));
+            let _ = parse_tree(&text);
+        }
+    }
+}
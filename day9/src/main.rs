@@ -1,70 +1,254 @@
-fn visit_data(input: &str, visitor: &mut Visitor) {
-    let mut depth = 0;
-    let mut in_garbage = false;
-    let mut escaped = false;
-    let mut garbage_length = 0;
-
-    for ch in input.chars() {
-        match ch {
-            '{' if !in_garbage => {
-                depth += 1;
-                visitor.begin_group(depth);
-            },
-            '}' if !in_garbage => {
-                depth -= 1;
-            },
-            // Only occurs as a separator within groups, ignore it
-            ',' if !in_garbage => (), 
-            '<' if !in_garbage => {
-                in_garbage = true;
-            },
-            '>' if in_garbage && !escaped => {
-                in_garbage = false;
-                visitor.add_garbage(garbage_length);
-                garbage_length = 0;
-            },
-            '!' if in_garbage && !escaped => {
-                escaped = true;
-            },
-            _ => if in_garbage {
-                if !escaped {
-                    garbage_length += 1;
-                }
-                escaped = false;
-            } else {
-                panic!("Invalid character in input: {}", ch);
-            },
+mod ast;
+mod generate;
+mod stream;
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+
+use stream::StreamParser;
+
+/// Receives events from `visit_data`. Every method does nothing by default,
+/// so visitors only need to implement the events they care about. Offsets
+/// are byte offsets into the input.
+trait StreamVisitor {
+    /// A group was opened at `offset`. The outermost group has depth 1.
+    fn begin_group(&mut self, _depth: usize, _offset: usize) {}
+
+    /// The group at `depth` was closed at `offset`.
+    fn end_group(&mut self, _depth: usize, _offset: usize) {}
+
+    /// Garbage was opened by the `<` at `offset`.
+    fn begin_garbage(&mut self, _offset: usize) {}
+
+    /// Garbage was closed by the `>` at `offset`. `raw` is everything between
+    /// the angle brackets, and `content` is the same with every `!` and the
+    /// character it cancels removed.
+    fn end_garbage(&mut self, _offset: usize, _raw: &str, _content: &str) {}
+
+    /// The character `ch` at `offset` was cancelled by the `!` before it.
+    fn cancelled(&mut self, _ch: char, _offset: usize) {}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ErrorKind {
+    /// A `}` with no open group to close.
+    UnbalancedClose,
+    /// A group that was still open at the end of the input.
+    UnterminatedGroup,
+    /// Garbage that was still open at the end of the input.
+    UnterminatedGarbage,
+    /// A character that isn't allowed outside garbage.
+    UnexpectedChar(char),
+    /// The input contains no group or garbage at all.
+    Empty,
+    /// A second group or garbage block at the top level, where only one is
+    /// expected.
+    MultipleRoots,
+}
+
+/// A problem with the input. `offset` is the byte offset of the offending
+/// character, or of the opening `{` or `<` for unterminated blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct StreamError {
+    offset: usize,
+    kind: ErrorKind,
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            ErrorKind::UnbalancedClose => write!(f, "unbalanced '}}'")?,
+            ErrorKind::UnterminatedGroup => write!(f, "unterminated group")?,
+            ErrorKind::UnterminatedGarbage => write!(f, "unterminated garbage")?,
+            ErrorKind::UnexpectedChar(ch) => write!(f, "unexpected character {:?}", ch)?,
+            ErrorKind::Empty => write!(f, "no group or garbage")?,
+            ErrorKind::MultipleRoots => write!(f, "more than one top-level group or garbage")?,
         }
+        write!(f, " at offset {}", self.offset)
     }
 }
 
-struct Visitor {
+/// Scans the input, stopping at the first error.
+fn visit_data<V: StreamVisitor>(input: &str, visitor: &mut V) -> Result<(), StreamError> {
+    match scan(input, visitor, false).first() {
+        Some(&error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// Scans the whole input, skipping over unexpected characters and stray
+/// `}`s, and returns every problem found.
+fn visit_data_recovering<V: StreamVisitor>(input: &str, visitor: &mut V) -> Vec<StreamError> {
+    scan(input, visitor, true)
+}
+
+fn scan<V: StreamVisitor>(input: &str, visitor: &mut V, recover: bool) -> Vec<StreamError> {
+    let mut parser = StreamParser::new(recover);
+    parser.feed(input.as_bytes(), visitor);
+    parser.finish(visitor)
+}
+
+/// Computes the answers to both parts.
+struct Counter {
     score: usize,
     garbage: usize,
 }
 
-impl Visitor {
-    fn new() -> Visitor {
-        Visitor {
+impl Counter {
+    fn new() -> Counter {
+        Counter {
             score: 0,
             garbage: 0,
         }
     }
+}
 
-    fn begin_group(&mut self, depth: usize) {
+impl StreamVisitor for Counter {
+    fn begin_group(&mut self, depth: usize, _offset: usize) {
         self.score += depth;
     }
 
-    fn add_garbage(&mut self, length: usize) {
-        self.garbage += length;
+    fn end_garbage(&mut self, _offset: usize, _raw: &str, content: &str) {
+        self.garbage += content.chars().count();
     }
 }
 
 fn main() {
     let input = include_str!("input.txt");
 
-    let mut visitor = Visitor::new();
-    visit_data(input, &mut visitor);
-    println!("Part 1 = {}", visitor.score);
-    println!("Part 2 = {}", visitor.garbage);
+    if let Some("--check") = env::args().nth(1).as_deref() {
+        let path = env::args().nth(2).expect("Usage: --check FILE");
+        let input = fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("Unable to read {}: {}", path, e));
+        let mut counter = Counter::new();
+        let errors = visit_data_recovering(input.trim_end(), &mut counter);
+        for error in errors.iter() {
+            println!("{}: {}", path, error);
+        }
+        println!("{} errors, score {}, garbage {}", errors.len(), counter.score, counter.garbage);
+        return;
+    }
+
+    if let Some("--generate") = env::args().nth(1).as_deref() {
+        // Prints a random stream, with its answers on standard error
+        let seed = env::args().nth(2)
+            .map(|s| s.parse().expect("Invalid seed"))
+            .unwrap_or(0);
+        let stream = generate::generate(&generate::Config::default(), &mut generate::Rng::new(seed));
+        println!("{}", stream.text);
+        eprintln!("Part 1 = {}", stream.score);
+        eprintln!("Part 2 = {}", stream.garbage);
+        return;
+    }
+
+    if let Some("--stream") = env::args().nth(1).as_deref() {
+        // Reads a stream from standard input as it arrives
+        let mut counter = Counter::new();
+        let stdin = io::stdin();
+        let errors = stream::visit_reader(stdin.lock(), &mut counter, true)
+            .expect("Unable to read standard input");
+        for error in errors.iter() {
+            eprintln!("{}", error);
+        }
+        println!("Part 1 = {}", counter.score);
+        println!("Part 2 = {}", counter.garbage);
+        return;
+    }
+
+    if env::args().nth(1).as_deref() == Some("--stats") {
+        let tree = ast::parse_tree(input).expect("Invalid input");
+        println!("Score: {}, garbage: {}", tree.score(), tree.garbage_count());
+        if let Some((depth, group)) = tree.deepest_group() {
+            println!("Deepest group: depth {} at bytes {:?}", depth, group.span());
+        }
+        for (depth, count) in tree.groups_per_depth().iter().enumerate() {
+            println!("Groups at depth {}: {}", depth + 1, count);
+        }
+        if let Some(garbage) = tree.largest_garbage() {
+            println!("Largest garbage: {}", garbage);
+        }
+        return;
+    }
+
+    let mut counter = Counter::new();
+    visit_data(input, &mut counter).expect("Invalid input");
+    println!("Part 1 = {}", counter.score);
+    println!("Part 2 = {}", counter.garbage);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn count(input: &str) -> (usize, usize) {
+        let mut counter = Counter::new();
+        visit_data(input, &mut counter).unwrap();
+        (counter.score, counter.garbage)
+    }
+
+    #[test]
+    fn test_examples() {
+        assert_eq!(count("{}"), (1, 0));
+        assert_eq!(count("{{{},{},{{}}}}"), (16, 0));
+        assert_eq!(count("{<a>,<a>,<a>,<a>}"), (1, 4));
+        assert_eq!(count("{{<!!>},{<!!>},{<!!>},{<!!>}}"), (9, 0));
+        assert_eq!(count("{{<a!>},{<a!>},{<a!>},{<ab>}}"), (3, 17));
+        assert_eq!(count("{<{o\"i!a,<{i<a>}"), (1, 10));
+    }
+
+    #[derive(Default)]
+    struct Events(Vec<String>);
+
+    impl StreamVisitor for Events {
+        fn begin_group(&mut self, depth: usize, offset: usize) {
+            self.0.push(format!("{{{}@{}", depth, offset));
+        }
+
+        fn end_group(&mut self, depth: usize, offset: usize) {
+            self.0.push(format!("}}{}@{}", depth, offset));
+        }
+
+        fn begin_garbage(&mut self, offset: usize) {
+            self.0.push(format!("<@{}", offset));
+        }
+
+        fn end_garbage(&mut self, offset: usize, raw: &str, content: &str) {
+            self.0.push(format!(">@{} {:?} {:?}", offset, raw, content));
+        }
+
+        fn cancelled(&mut self, ch: char, offset: usize) {
+            self.0.push(format!("!{}@{}", ch, offset));
+        }
+    }
+
+    #[test]
+    fn test_events() {
+        let mut events = Events::default();
+        visit_data("{{},<a!>b>}", &mut events).unwrap();
+        assert_eq!(events.0, vec![
+            "{1@0", "{2@1", "}2@2", "<@4", "!>@7", ">@9 \"a!>b\" \"ab\"", "}1@10",
+        ]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |offset, kind| StreamError { offset, kind };
+        let mut counter = Counter::new();
+        assert_eq!(visit_data("{}}", &mut counter), Err(error(2, ErrorKind::UnbalancedClose)));
+        assert_eq!(visit_data("{{<>}", &mut counter), Err(error(0, ErrorKind::UnterminatedGroup)));
+        assert_eq!(visit_data("{<!>}", &mut counter), Err(error(1, ErrorKind::UnterminatedGarbage)));
+        assert_eq!(visit_data("{a}", &mut counter), Err(error(1, ErrorKind::UnexpectedChar('a'))));
+
+        let mut counter = Counter::new();
+        let errors = visit_data_recovering("}{{x},{<a>}},{<b", &mut counter);
+        assert_eq!(errors, vec![
+            error(0, ErrorKind::UnbalancedClose),
+            error(3, ErrorKind::UnexpectedChar('x')),
+            error(14, ErrorKind::UnterminatedGarbage),
+            error(13, ErrorKind::UnterminatedGroup),
+        ]);
+        assert_eq!((counter.score, counter.garbage), (1 + 2 + 2 + 1, 1));
+    }
 }
@@ -0,0 +1,280 @@
+//! A push-style scanner that can be fed its input in chunks.
+
+use std::io::{self, Read};
+use std::str;
+
+use super::{ErrorKind, StreamError, StreamVisitor};
+
+/// Scanner state that persists between calls to `feed`, so that a stream
+/// can be processed as it arrives. Chunks may be split anywhere, even in
+/// the middle of a UTF-8 sequence.
+pub struct StreamParser {
+    recover: bool,
+    errors: Vec<StreamError>,
+    /// Whitespace found outside garbage since the last other character. It
+    /// is only an error if something follows it, so that a trailing newline
+    /// is allowed.
+    trailing: Vec<StreamError>,
+    /// The offset of the next character.
+    offset: usize,
+    /// The start of a UTF-8 sequence that was cut off by the end of a chunk.
+    pending: Vec<u8>,
+    /// The offsets of the currently open groups.
+    open: Vec<usize>,
+    in_garbage: bool,
+    escaped: bool,
+    garbage_start: usize,
+    raw: String,
+    content: String,
+}
+
+impl StreamParser {
+    /// Creates a parser that either stops at the first error or, if
+    /// `recover` is set, skips over problems and reports them all.
+    pub fn new(recover: bool) -> StreamParser {
+        StreamParser {
+            recover,
+            errors: Vec::new(),
+            trailing: Vec::new(),
+            offset: 0,
+            pending: Vec::new(),
+            open: Vec::new(),
+            in_garbage: false,
+            escaped: false,
+            garbage_start: 0,
+            raw: String::new(),
+            content: String::new(),
+        }
+    }
+
+    fn stopped(&self) -> bool {
+        !self.recover && !self.errors.is_empty()
+    }
+
+    pub fn feed<V: StreamVisitor>(&mut self, mut chunk: &[u8], visitor: &mut V) {
+        // Complete a sequence cut off by the previous chunk, a byte at a time
+        while !self.pending.is_empty() && !chunk.is_empty() {
+            let mut bytes = std::mem::take(&mut self.pending);
+            bytes.push(chunk[0]);
+            chunk = &chunk[1..];
+            self.feed_bytes(&bytes, visitor);
+        }
+        self.feed_bytes(chunk, visitor);
+    }
+
+    /// Processes `bytes`, keeping an incomplete sequence at the end for the
+    /// next chunk. Invalid sequences are replaced by U+FFFD.
+    fn feed_bytes<V: StreamVisitor>(&mut self, mut bytes: &[u8], visitor: &mut V) {
+        loop {
+            match str::from_utf8(bytes) {
+                Ok(text) => {
+                    self.push_str(text, visitor);
+                    return;
+                },
+                Err(e) => {
+                    let (valid, rest) = bytes.split_at(e.valid_up_to());
+                    self.push_str(str::from_utf8(valid).unwrap(), visitor);
+                    match e.error_len() {
+                        Some(len) => {
+                            self.push_char('\u{FFFD}', len, visitor);
+                            bytes = &rest[len..];
+                        },
+                        None => {
+                            self.pending = rest.to_vec();
+                            return;
+                        },
+                    }
+                },
+            }
+        }
+    }
+
+    fn push_str<V: StreamVisitor>(&mut self, text: &str, visitor: &mut V) {
+        for ch in text.chars() {
+            self.push_char(ch, ch.len_utf8(), visitor);
+        }
+    }
+
+    fn push_char<V: StreamVisitor>(&mut self, ch: char, len: usize, visitor: &mut V) {
+        if self.in_garbage || !ch.is_whitespace() {
+            self.errors.append(&mut self.trailing);
+        }
+        if self.stopped() {
+            return;
+        }
+        let offset = self.offset;
+        self.offset += len;
+
+        if self.in_garbage && (self.escaped || ch != '>') {
+            self.raw.push(ch);
+        }
+        let kind = match ch {
+            _ if self.escaped => {
+                self.escaped = false;
+                visitor.cancelled(ch, offset);
+                return;
+            },
+            '{' if !self.in_garbage => {
+                self.open.push(offset);
+                visitor.begin_group(self.open.len(), offset);
+                return;
+            },
+            '}' if !self.in_garbage => {
+                if self.open.is_empty() {
+                    ErrorKind::UnbalancedClose
+                } else {
+                    visitor.end_group(self.open.len(), offset);
+                    self.open.pop();
+                    return;
+                }
+            },
+            // Only occurs as a separator within groups, ignore it
+            ',' if !self.in_garbage => return,
+            '<' if !self.in_garbage => {
+                self.in_garbage = true;
+                self.garbage_start = offset;
+                self.raw.clear();
+                self.content.clear();
+                visitor.begin_garbage(offset);
+                return;
+            },
+            '>' if self.in_garbage => {
+                self.in_garbage = false;
+                visitor.end_garbage(offset, &self.raw, &self.content);
+                return;
+            },
+            '!' if self.in_garbage => {
+                self.escaped = true;
+                return;
+            },
+            _ if self.in_garbage => {
+                self.content.push(ch);
+                return;
+            },
+            _ if ch.is_whitespace() => {
+                self.trailing.push(StreamError { offset, kind: ErrorKind::UnexpectedChar(ch) });
+                return;
+            },
+            _ => ErrorKind::UnexpectedChar(ch),
+        };
+        self.errors.push(StreamError { offset, kind });
+    }
+
+    /// Signals the end of the input, reporting anything left unterminated,
+    /// and returns the errors found. Without recovery there is at most one.
+    pub fn finish<V: StreamVisitor>(mut self, visitor: &mut V) -> Vec<StreamError> {
+        if !self.pending.is_empty() {
+            let len = self.pending.len();
+            self.push_char('\u{FFFD}', len, visitor);
+        }
+        if self.in_garbage {
+            self.errors.push(StreamError {
+                offset: self.garbage_start,
+                kind: ErrorKind::UnterminatedGarbage,
+            });
+        }
+        for &offset in self.open.iter() {
+            self.errors.push(StreamError {
+                offset,
+                kind: ErrorKind::UnterminatedGroup,
+            });
+        }
+        if !self.recover {
+            self.errors.truncate(1);
+        }
+        self.errors
+    }
+}
+
+/// Scans everything `reader` produces, a chunk at a time.
+pub fn visit_reader<R, V>(mut reader: R, visitor: &mut V, recover: bool) -> io::Result<Vec<StreamError>>
+    where R: Read, V: StreamVisitor
+{
+    let mut parser = StreamParser::new(recover);
+    let mut buffer = [0; 8192];
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        parser.feed(&buffer[..read], visitor);
+    }
+    Ok(parser.finish(visitor))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Records every event and error as text, for comparing runs.
+    #[derive(Default)]
+    struct Log(Vec<String>);
+
+    impl StreamVisitor for Log {
+        fn begin_group(&mut self, depth: usize, offset: usize) {
+            self.0.push(format!("{{{}@{}", depth, offset));
+        }
+
+        fn end_group(&mut self, depth: usize, offset: usize) {
+            self.0.push(format!("}}{}@{}", depth, offset));
+        }
+
+        fn end_garbage(&mut self, offset: usize, raw: &str, content: &str) {
+            self.0.push(format!(">@{} {:?} {:?}", offset, raw, content));
+        }
+
+        fn cancelled(&mut self, ch: char, offset: usize) {
+            self.0.push(format!("!{}@{}", ch, offset));
+        }
+    }
+
+    fn run_chunks(chunks: &[&[u8]]) -> Vec<String> {
+        let mut log = Log::default();
+        let mut parser = StreamParser::new(true);
+        for chunk in chunks {
+            parser.feed(chunk, &mut log);
+        }
+        for error in parser.finish(&mut log) {
+            log.0.push(error.to_string());
+        }
+        log.0
+    }
+
+    #[test]
+    fn test_every_split() {
+        let input = "{{<!!>},{<a!>},{<\u{e9}!\u{1f600}x>},{<{o\"i!a,<{i<a>}}},\u{e9}}\u{ff}{<!".as_bytes();
+        let expected = run_chunks(&[input]);
+        for split in 0..=input.len() {
+            let (a, b) = input.split_at(split);
+            assert_eq!(run_chunks(&[a, b]), expected, "split at {}", split);
+        }
+    }
+
+    #[test]
+    fn test_trailing_whitespace() {
+        assert_eq!(run_chunks(&[b"{}\n"]), vec!["{1@0", "}1@1"]);
+        assert_eq!(run_chunks(&[b"{}", b" \r\n"]), run_chunks(&[b"{}"]));
+        assert_eq!(run_chunks(&[b"{ }\n"]), vec![
+            "{1@0",
+            "}1@2",
+            "unexpected character ' ' at offset 1",
+        ]);
+    }
+
+    #[test]
+    fn test_byte_at_a_time() {
+        let input = include_str!("input.txt");
+        let mut expected = ::Counter::new();
+        ::visit_data(input, &mut expected).unwrap();
+
+        let mut counter = ::Counter::new();
+        let mut parser = StreamParser::new(false);
+        for byte in input.bytes() {
+            parser.feed(&[byte], &mut counter);
+        }
+        assert_eq!(parser.finish(&mut counter), vec![]);
+        assert_eq!((counter.score, counter.garbage), (expected.score, expected.garbage));
+    }
+}